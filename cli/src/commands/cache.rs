@@ -0,0 +1,48 @@
+use crate::error::CliError;
+use anyhow::Result;
+use bedrockci::cache::{cache_key, export_server, import_server};
+use bedrockci::download::server::get_latest_version;
+use bedrockci::server::{Channel, PREVIEW_ALIAS, resolve_installed_version};
+use bedrockci::server_path::get_server_path;
+use colored::*;
+use std::path::Path;
+
+/// Version spec of `cache key` that resolves to the latest available release.
+const LATEST_ALIAS: &str = "latest";
+
+/// Handles the cache export command, writing an installed server to a cache bundle.
+pub async fn handle_export(version: String, bundle: String) -> Result<()> {
+    let server_path = get_server_path(false)?;
+    let name = resolve_installed_version(Some(&version))?
+        .ok_or_else(|| CliError::Usage("No preview server versions installed".to_string()))?;
+
+    println!("Exporting server version {} to {}...", name, bundle);
+    export_server(&server_path, &name, Path::new(&bundle))?;
+    println!("{}", format!("✓ Exported server version {}", name).green());
+    Ok(())
+}
+
+/// Handles the cache import command, installing a server from a cache bundle.
+pub async fn handle_import(bundle: String, force: bool) -> Result<()> {
+    let server_path = get_server_path(true)?;
+
+    println!("Importing {}...", bundle);
+    let name = import_server(&server_path, Path::new(&bundle), force)?;
+    println!("{}", format!("✓ Imported server version {}", name).green());
+    Ok(())
+}
+
+/// Handles the cache key command, printing a stable cache key for a server version.
+///
+/// `latest` and `preview` are resolved to the latest available version, so the key can be
+/// computed before anything is installed.
+pub async fn handle_key(version: String) -> Result<()> {
+    let name = match version.as_str() {
+        LATEST_ALIAS => get_latest_version(Channel::Release).await?,
+        PREVIEW_ALIAS => Channel::Preview.dir_name(&get_latest_version(Channel::Preview).await?),
+        _ => version,
+    };
+
+    println!("{}", cache_key(&name));
+    Ok(())
+}
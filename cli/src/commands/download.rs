@@ -1,47 +1,174 @@
+use crate::commands::{EXIT_ALREADY_UP_TO_DATE, EXIT_USAGE};
+use crate::error::CliError;
 use anyhow::Result;
-use bedrockci::download::server::{ServerDownloadError, download_server, get_latest_version};
+use bedrockci::download::server::{
+    DownloadOptions, EULA_NOT_ACCEPTED_TEXT, ServerDownloadError, check_for_update,
+    download_server, get_latest_version, install_server_from_file,
+};
+use bedrockci::lock::{LOCK_FILE_NAME, LockFile};
+use bedrockci::server::{Channel, PREVIEW_ALIAS};
 use bedrockci::server_path::get_server_path;
+use std::path::Path;
 
-pub async fn handle_download(
-    version: Option<String>,
-    accepted_eula_and_privacy_policy: bool,
-    force_reinstall: bool,
-) -> Result<()> {
+/// Arguments of the download command.
+pub struct DownloadArgs {
+    pub version: Option<String>,
+    pub accepted_eula_and_privacy_policy: bool,
+    pub force_reinstall: bool,
+    pub sha256: Option<String>,
+    pub from_file: Option<String>,
+    pub base_url: Option<String>,
+    pub preview: bool,
+    pub if_newer: bool,
+    pub locked: bool,
+    pub lock: bool,
+    pub verify_version: bool,
+}
+
+pub async fn handle_download(args: DownloadArgs) -> Result<()> {
+    let DownloadArgs {
+        version,
+        accepted_eula_and_privacy_policy,
+        force_reinstall,
+        sha256,
+        from_file,
+        base_url,
+        preview,
+        if_newer,
+        locked,
+        lock: write_lock,
+        verify_version,
+    } = args;
     let path = get_server_path(true)?;
-    let version = match version {
-        Some(v) => v,
+
+    // `--locked` installs exactly the build pinned in the lock file
+    let lock = if locked {
+        let lock = LockFile::read(Path::new(LOCK_FILE_NAME))?.ok_or_else(|| {
+            CliError::Usage(format!(
+                "--locked requires a {} in the current directory",
+                LOCK_FILE_NAME
+            ))
+        })?;
+        println!("Using locked server version: {}", lock.dir_name());
+        Some(lock)
+    } else {
+        None
+    };
+
+    // `--version preview` is shorthand for `--preview` with the latest preview build
+    let preview = preview || version.as_deref() == Some(PREVIEW_ALIAS);
+    let version = version.filter(|v| v != PREVIEW_ALIAS);
+    let channel = match &lock {
+        Some(lock) => lock.channel,
+        None if preview => Channel::Preview,
+        None => Channel::Release,
+    };
+
+    let options = DownloadOptions {
+        force_reinstall,
+        expected_sha256: lock.as_ref().map(|lock| lock.sha256.clone()).or(sha256),
+        // Mirrors take precedence, the checksum still guarantees the locked build
+        base_url: base_url.or_else(|| {
+            lock.as_ref()
+                .and_then(|lock| lock.url.rsplit_once('/'))
+                .map(|(base, _)| base.to_string())
+        }),
+        channel,
+        verify_version,
+        ..Default::default()
+    };
+
+    let version = if let Some(lock) = &lock {
+        Some(lock.version.clone())
+    } else if if_newer {
+        // Only download when the latest version is newer than anything installed
+        let check = check_for_update(channel).await?;
+        if !check.is_outdated() {
+            println!(
+                "Server {} is already the latest version",
+                check.installed.as_deref().unwrap_or_default()
+            );
+            std::process::exit(EXIT_ALREADY_UP_TO_DATE);
+        }
+        println!("Newer server version available: {}", check.latest);
+        Some(check.latest)
+    } else {
+        version
+    };
+
+    let result = match from_file {
+        Some(zip_path) => install_server_from_file(
+            Path::new(&zip_path),
+            version.as_deref(),
+            path.clone(),
+            accepted_eula_and_privacy_policy,
+            &options,
+        )
+        .await
+        .inspect(|installed| println!("Installed server version {}", installed)),
         None => {
-            let latest_version = get_latest_version().await?;
-            println!("No version specified, using latest version: {}", latest_version);
-            latest_version
+            let version = match version {
+                Some(v) => v,
+                None => {
+                    let latest_version = get_latest_version(channel).await?;
+                    println!(
+                        "No version specified, using latest version: {}",
+                        latest_version
+                    );
+                    latest_version
+                }
+            };
+
+            download_server(
+                &version,
+                path.clone(),
+                accepted_eula_and_privacy_policy,
+                &options,
+            )
+            .await
+            .map(|_| version)
         }
     };
 
-    match download_server(
-        &version,
-        path,
-        accepted_eula_and_privacy_policy,
-        force_reinstall,
-    )
-    .await
-    {
-        Ok(_) => println!("Server downloaded successfully!"),
+    let installed = match result {
+        Ok(version) => {
+            println!("Server downloaded successfully!");
+            channel.dir_name(&version)
+        }
         Err(ServerDownloadError::EulaAndPrivacyPolicyNotAccepted) => {
+            println!("{}", EULA_NOT_ACCEPTED_TEXT);
             eprintln!(
                 "Please run the command with the --accept-eula flag to accept the EULA and Privacy Policy."
             );
             eprintln!("You must accept the EULA and Privacy Policy to download the server.");
-            std::process::exit(1);
+            std::process::exit(EXIT_USAGE);
         }
         Err(ServerDownloadError::ServerAlreadyInstalled(v)) => {
-            println!("Server {} already installed, use --force-reinstall to download again", v);
-            std::process::exit(0);
-        }
-        Err(e) => {
-            eprintln!("Error downloading server: {}", e);
-            std::process::exit(1);
+            println!(
+                "Server {} already installed, use --force-reinstall to download again",
+                v
+            );
+            v
         }
+        // Reported with its sources and exit code by main
+        Err(e) => return Err(e.into()),
+    };
+
+    match &lock {
+        Some(lock) => lock.check_installed(&path)?,
+        None if write_lock => update_lock_file(&path, &installed),
+        None => {}
     }
 
     Ok(())
 }
+
+/// Pins the installed server in the lock file of the current directory.
+fn update_lock_file(server_path: &Path, installed: &str) {
+    match LockFile::from_install(server_path, installed)
+        .and_then(|lock| lock.write(Path::new(LOCK_FILE_NAME)))
+    {
+        Ok(()) => println!("Pinned server version {} in {}", installed, LOCK_FILE_NAME),
+        Err(e) => eprintln!("Warning: Failed to update {}: {}", LOCK_FILE_NAME, e),
+    }
+}
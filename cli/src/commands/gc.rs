@@ -0,0 +1,21 @@
+use anyhow::Result;
+use bedrockci::dedup::gc;
+use bedrockci::server_path::get_server_path;
+use colored::*;
+
+/// Handles the gc command, sharing identical files between installed versions and removing
+/// stored files no version uses any more.
+pub async fn handle_gc() -> Result<()> {
+    let report = gc(&get_server_path(false)?)?;
+
+    println!(
+        "Shared {} files ({} bytes) between installed versions",
+        report.dedup.linked_files, report.dedup.bytes_saved
+    );
+    println!(
+        "Removed {} unused stored files ({} bytes)",
+        report.removed_objects, report.bytes_freed
+    );
+    println!("{}", "✓ Garbage collection complete".green());
+    Ok(())
+}
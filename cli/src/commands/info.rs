@@ -0,0 +1,72 @@
+use anyhow::Result;
+use bedrockci::server::server_info;
+
+/// Handles the info command, printing details about an installed server version.
+pub async fn handle_info(version: String) -> Result<()> {
+    let info = server_info(&version)?;
+
+    println!("Version:   {}", info.name);
+    println!(
+        "Channel:   {}",
+        format!("{:?}", info.channel).to_lowercase()
+    );
+    println!("Path:      {}", info.path.display());
+    println!("Size:      {}", format_size(info.size_bytes));
+    println!(
+        "Installed: {}",
+        info.installed_at
+            .map(format_timestamp)
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+    println!(
+        "Source:    {}",
+        info.source_url.as_deref().unwrap_or("unknown")
+    );
+
+    Ok(())
+}
+
+/// Formats a byte count using binary units, e.g. `123.4 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time.
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // Civil-from-days conversion, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
@@ -1,13 +1,33 @@
 use anyhow::Result;
-use bedrockci::server::list_servers;
+use bedrockci::server::{Channel, ServerInfo, list_servers, server_info};
 
-pub async fn handle_list_servers() -> Result<()> {
+pub async fn handle_list_servers(json: bool) -> Result<()> {
     let versions = list_servers()?;
 
+    if json {
+        let infos = versions
+            .iter()
+            .map(|name| server_info(name))
+            .collect::<Result<Vec<ServerInfo>, _>>()?;
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+        return Ok(());
+    }
+
+    let (previews, releases): (Vec<_>, Vec<_>) = versions
+        .iter()
+        .partition(|name| Channel::from_dir_name(name).0 == Channel::Preview);
+
     println!("Downloaded server versions:");
-    for version in versions {
+    for version in releases {
         println!("{}", version);
     }
 
+    if !previews.is_empty() {
+        println!("\nDownloaded preview versions:");
+        for version in previews {
+            println!("{}", version);
+        }
+    }
+
     Ok(())
 }
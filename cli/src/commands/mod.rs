@@ -1,4 +1,29 @@
+pub mod cache;
 pub mod download;
+pub mod gc;
+pub mod info;
 pub mod list_servers;
+pub mod outdated;
+pub mod prune;
+pub mod remove;
 pub mod run;
 pub mod validate;
+pub mod verify;
+
+/// Exit code when validation found errors, or warnings in fail on warn mode.
+pub const EXIT_VALIDATION_FAILED: i32 = 1;
+/// Exit code when a command was used incorrectly, e.g. with a version that isn't installed or
+/// invalid arguments. `EX_USAGE` from sysexits.h, as the small codes are taken by `outdated`
+/// and `download --if-newer`.
+pub const EXIT_USAGE: i32 = 64;
+/// Exit code when something outside the command's control failed, e.g. the network, the file
+/// system or the server process.
+pub const EXIT_INFRASTRUCTURE: i32 = 4;
+/// Exit code of `outdated` when a newer server version is available.
+pub const EXIT_UPDATE_AVAILABLE: i32 = 2;
+/// Exit code of `download --if-newer` when the newest version is already installed.
+pub const EXIT_ALREADY_UP_TO_DATE: i32 = 3;
+/// Exit code of `validate` when it was interrupted by Ctrl+C (128 + SIGINT).
+pub const EXIT_INTERRUPTED: i32 = 130;
+/// Exit code of `validate` when it was stopped by SIGTERM (128 + SIGTERM).
+pub const EXIT_TERMINATED: i32 = 143;
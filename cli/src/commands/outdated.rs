@@ -0,0 +1,34 @@
+use crate::commands::EXIT_UPDATE_AVAILABLE;
+use anyhow::Result;
+use bedrockci::download::server::check_for_update;
+use bedrockci::server::Channel;
+use colored::*;
+
+/// Handles the outdated command, comparing the newest installed server against the latest one.
+///
+/// Exits with [`EXIT_UPDATE_AVAILABLE`] if a newer version can be downloaded.
+pub async fn handle_outdated(preview: bool) -> Result<()> {
+    let channel = if preview {
+        Channel::Preview
+    } else {
+        Channel::Release
+    };
+    let check = check_for_update(channel).await?;
+
+    println!(
+        "Installed: {}",
+        check.installed.as_deref().unwrap_or("none")
+    );
+    println!("Latest:    {}", check.latest);
+
+    if check.is_outdated() {
+        println!(
+            "{}",
+            format!("A newer server version is available: {}", check.latest).yellow()
+        );
+        std::process::exit(EXIT_UPDATE_AVAILABLE);
+    }
+
+    println!("{}", "✓ Server is up to date".green());
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use crate::lock::ensure_not_locked;
+use anyhow::Result;
+use bedrockci::server::{prune_servers, servers_to_prune};
+use colored::*;
+
+/// Handles the prune command, removing all but the newest `keep` versions of each channel.
+///
+/// Fails without removing anything if the version pinned in `bedrockci.lock` would be removed,
+/// unless `force` is set.
+pub async fn handle_prune(keep: usize, force: bool) -> Result<()> {
+    if !force {
+        for name in servers_to_prune(keep)? {
+            ensure_not_locked(&name)?;
+        }
+    }
+
+    let removed = prune_servers(keep)?;
+
+    if removed.is_empty() {
+        println!("Nothing to prune");
+        return Ok(());
+    }
+
+    for version in &removed {
+        println!("  {} {}", "removed:".yellow(), version);
+    }
+    println!(
+        "{}",
+        format!("✓ Pruned {} server versions", removed.len()).green()
+    );
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use crate::lock::ensure_not_locked;
+use anyhow::Result;
+use bedrockci::server::remove_server;
+use colored::*;
+
+/// Handles the remove command, deleting an installed server version.
+///
+/// The version pinned in `bedrockci.lock` is only removed with `force`.
+pub async fn handle_remove(version: String, force: bool) -> Result<()> {
+    if !force {
+        ensure_not_locked(&version)?;
+    }
+    remove_server(&version)?;
+    println!(
+        "{}",
+        format!("✓ Removed server version {}", version).green()
+    );
+    Ok(())
+}
@@ -1,26 +1,32 @@
-use anyhow::Result;
-use bedrockci::server::list_servers;
+use crate::error::CliError;
+use crate::lock::resolve_server_version;
+use crate::signal::ShutdownSignals;
+use anyhow::{Context, Result};
+use bedrockci::instance::ServerInstance;
+use bedrockci::properties::{
+    SERVER_PROPERTIES_FILE_NAME, ServerProperties, read_server_properties,
+};
 use bedrockci::server_path::get_server_path;
+use bedrockci::snapshot::SnapshotLock;
 use bedrockci::validate::symlink_test_packs;
 use colored::*;
 use std::path::Path;
-use std::process::Command;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command as TokioCommand;
-use tokio::signal;
 
 /// Handles the run command for spinning up a Bedrock server with specified packs.
 ///
 /// This command sets up symlinks for the provided behavior and resource packs,
 /// then starts a Bedrock server that will keep running until manually stopped.
 /// Unlike the validate command, this is designed for interactive testing and
-/// development workflows.
+/// development workflows. The server.properties overrides only apply while the
+/// server runs, the server's own server.properties is restored afterwards.
 ///
 /// # Arguments
 ///
 /// * `resource_pack` - Path to the resource pack directory
 /// * `behavior_pack` - Path to the behavior pack directory
-/// * `version` - Optional server version to use (defaults to latest installed)
+/// * `version` - Optional server version to use (defaults to the locked or latest installed)
+/// * `locked` - Require the installed server to match `bedrockci.lock`
+/// * `properties` - server.properties values to override while the server runs
 /// * `verbose` - Whether to show verbose server output
 ///
 /// # Returns
@@ -31,6 +37,8 @@ pub async fn handle_run(
     resource_pack: String,
     behavior_pack: String,
     version: Option<String>,
+    locked: bool,
+    properties: ServerProperties,
     verbose: bool,
 ) -> Result<()> {
     let resource_path = Path::new(&resource_pack);
@@ -38,44 +46,41 @@ pub async fn handle_run(
 
     // Validate pack paths exist and are directories
     if !resource_path.exists() {
-        anyhow::bail!("Resource pack not found at: {}", resource_pack);
+        return Err(
+            CliError::Usage(format!("Resource pack not found at: {}", resource_pack)).into(),
+        );
     }
     if !resource_path.is_dir() {
-        anyhow::bail!("Resource pack path is not a directory: {}", resource_pack);
+        return Err(CliError::Usage(format!(
+            "Resource pack path is not a directory: {}",
+            resource_pack
+        ))
+        .into());
     }
     if !behavior_path.exists() {
-        anyhow::bail!("Behavior pack not found at: {}", behavior_pack);
+        return Err(
+            CliError::Usage(format!("Behavior pack not found at: {}", behavior_pack)).into(),
+        );
     }
     if !behavior_path.is_dir() {
-        anyhow::bail!("Behavior pack path is not a directory: {}", behavior_pack);
+        return Err(CliError::Usage(format!(
+            "Behavior pack path is not a directory: {}",
+            behavior_pack
+        ))
+        .into());
     }
 
-    let version = match version {
-        Some(v) => v,
-        None => {
-            let versions = list_servers()?;
-            if versions.is_empty() {
-                anyhow::bail!(
-                    "No server versions found. Please download a server version first using: bedrockci download"
-                );
-            }
-            println!(
-                "No version specified, using latest: {}",
-                versions.last().unwrap()
-            );
-            versions.last().unwrap().clone()
-        }
-    };
+    let version = resolve_server_version(version, locked)?;
 
     // Get server path from environment or use the specified version
     let server_path = get_server_path(false)?.join(&version);
 
     if !server_path.exists() {
-        anyhow::bail!(
+        return Err(CliError::Usage(format!(
             "Server version {} not found. Please download it first using: bedrockci download --version {}",
             version,
             version
-        );
+        )).into());
     }
 
     println!(
@@ -83,12 +88,23 @@ pub async fn handle_run(
         format!("Using server version: {}", version).cyan().bold()
     );
 
+    // Apply the overrides to the server's own properties, not those of a run that was killed
+    let lock = SnapshotLock::acquire(&server_path)?;
+    let mut overridden = read_server_properties(&server_path)?;
+    overridden.merge(&properties);
+    let snapshot = lock.capture(&[Path::new(SERVER_PROPERTIES_FILE_NAME)])?;
+    overridden.write(&server_path.join(SERVER_PROPERTIES_FILE_NAME))?;
+    for (key, value) in properties.iter() {
+        println!("{}", format!("Overriding {}={}", key, value).cyan());
+    }
+
     println!("{}", "Symlinking test packs to server directory...".cyan());
     symlink_test_packs(&server_path, behavior_path, resource_path)?;
     println!("{}", "Packs successfully linked to server".green());
 
     println!("{}", "Starting server...".cyan());
     start_and_run_server(&server_path, verbose).await?;
+    snapshot.restore()?;
 
     Ok(())
 }
@@ -96,10 +112,9 @@ pub async fn handle_run(
 /// Starts and runs the Bedrock server, monitoring its output until interrupted.
 ///
 /// This function handles the server lifecycle including:
-/// - Making the server executable
 /// - Starting the server process
-/// - Monitoring stdout/stderr output
-/// - Graceful shutdown on Ctrl+C
+/// - Monitoring its output
+/// - Graceful shutdown on Ctrl+C or SIGTERM
 ///
 /// # Arguments
 ///
@@ -111,37 +126,9 @@ pub async fn handle_run(
 /// * `Ok(())` - If the server ran and was stopped successfully
 /// * `Err(anyhow::Error)` - If there was an error during server execution
 async fn start_and_run_server(server_path: &Path, verbose: bool) -> Result<()> {
-    let server_exe = server_path.join("bedrock_server");
-    if !server_exe.exists() {
-        anyhow::bail!(
-            "bedrock_server executable not found in {}",
-            server_path.display()
-        );
-    }
-
-    println!("{}", "Making server executable...".cyan());
-    Command::new("chmod")
-        .arg("+x")
-        .arg(&server_exe)
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to make server executable: {}", e))?;
-
+    let mut signals = ShutdownSignals::register().context("Failed to install signal handlers")?;
     println!("{}", "Starting server process...".cyan());
-    let mut child = TokioCommand::new(&server_exe)
-        .current_dir(server_path)
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| anyhow::anyhow!("Failed to start server process: {}", e))?;
-
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or_else(|| anyhow::anyhow!("Failed to capture server stdout"))?;
-    let stderr = child
-        .stderr
-        .take()
-        .ok_or_else(|| anyhow::anyhow!("Failed to capture server stderr"))?;
+    let mut server = ServerInstance::start(server_path).await?;
 
     println!();
     println!(
@@ -154,26 +141,15 @@ async fn start_and_run_server(server_path: &Path, verbose: bool) -> Result<()> {
     }
     println!();
 
-    let mut stdout_reader = BufReader::new(stdout).lines();
-    let mut stderr_reader = BufReader::new(stderr).lines();
     let mut server_started = false;
 
     loop {
         tokio::select! {
-            Ok(Some(line)) = stdout_reader.next_line() => {
-                let line = line.trim();
-                if !line.is_empty() {
-                    process_server_line(line, &mut server_started, verbose);
-                }
+            Some(line) = server.next_line() => {
+                process_server_line(&line, &mut server_started, verbose);
             }
-            Ok(Some(line)) = stderr_reader.next_line() => {
-                let line = line.trim();
-                if !line.is_empty() {
-                    process_server_line(line, &mut server_started, verbose);
-                }
-            }
-            _ = signal::ctrl_c() => {
-                println!("\n{}", "Received Ctrl+C, stopping server...".yellow());
+            signal = signals.recv() => {
+                println!("\n{}", format!("Received {}, stopping server...", signal.name()).yellow());
                 break;
             }
             else => break
@@ -181,14 +157,7 @@ async fn start_and_run_server(server_path: &Path, verbose: bool) -> Result<()> {
     }
 
     println!("{}", "Stopping server...".cyan());
-    child
-        .kill()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to kill server process: {}", e))?;
-    child
-        .wait()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to wait for server to stop: {}", e))?;
+    server.stop().await?;
     println!("{}", "Server stopped successfully.".green());
 
     Ok(())
@@ -218,37 +187,36 @@ fn process_server_line(line: &str, server_started: &mut bool, verbose: bool) {
     }
 
     // Show important startup messages even in non-verbose mode
-    if !*server_started {
-        if line.contains("Starting Server")
+    if !*server_started
+        && (line.contains("Starting Server")
             || line.contains("IPv4 supported")
             || line.contains("IPv6 supported")
             || line.contains("Level Name:")
             || line.contains("Game mode:")
             || line.contains("Difficulty:")
-            || line.contains("opening worlds")
-        {
-            println!("{}", format!("{}", line).blue());
-            return;
-        }
+            || line.contains("opening worlds"))
+    {
+        println!("{}", line.blue());
+        return;
     }
 
     // In verbose mode, show all output
     if verbose {
         if line.contains("ERROR") {
-            println!("{}", format!("{}", line).red());
+            println!("{}", line.red());
         } else if line.contains("WARN") {
-            println!("{}", format!("{}", line).yellow());
+            println!("{}", line.yellow());
         } else if line.contains("INFO") {
-            println!("{}", format!("{}", line).blue());
+            println!("{}", line.blue());
         } else {
-            println!("{}", format!("{}", line).dimmed());
+            println!("{}", line.dimmed());
         }
     } else {
         // In non-verbose mode, only show errors, warnings, and important info
         if line.contains("ERROR") {
-            println!("{}", format!("{}", line).red());
+            println!("{}", line.red());
         } else if line.contains("WARN") {
-            println!("{}", format!("{}", line).yellow());
+            println!("{}", line.yellow());
         } else if line.contains("Player connected:")
             || line.contains("Player disconnected:")
             || line.contains("Player Spawned:")
@@ -259,7 +227,7 @@ fn process_server_line(line: &str, server_started: &mut bool, verbose: bool) {
                     || line.contains("Saving...")
                     || line.contains("Changes to the level are resumed")))
         {
-            println!("{}", format!("{}", line).blue());
+            println!("{}", line.blue());
         }
     }
 }
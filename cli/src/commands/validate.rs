@@ -1,57 +1,156 @@
-use anyhow::Result;
-use bedrockci::server::list_servers;
-use bedrockci::server_path::get_server_path;
-use bedrockci::validate::{start_server, symlink_test_packs, ValidationResult};
+use crate::commands::EXIT_INTERRUPTED;
+use crate::error::CliError;
+use crate::lock::resolve_server_version;
+use crate::signal::ShutdownSignals;
+use anyhow::{Context, Result};
+use bedrockci::CancellationToken;
+use bedrockci::properties::ServerProperties;
+use bedrockci::validate::{Timeouts, ValidationError, ValidationResult, Validator};
 use colored::*;
 use std::path::Path;
+use std::time::Duration;
 
-pub async fn handle_validate(
-    resource_pack: String,
-    behavior_pack: String,
-    only_warn: bool,
-    fail_on_warn: bool,
-    version: Option<String>,
-    last_log_timeout: Option<u64>,
-    verbose: bool,
-) -> Result<()> {
+/// Arguments of the validate command.
+pub struct ValidateArgs {
+    pub resource_pack: String,
+    pub behavior_pack: String,
+    pub only_warn: bool,
+    pub fail_on_warn: bool,
+    pub version: Option<String>,
+    pub locked: bool,
+    pub world: Option<String>,
+    /// server.properties overrides, see [`crate::properties::property_overrides`].
+    pub properties: ServerProperties,
+    /// Where to save the content log file written during validation, which also enables it.
+    pub content_log_file: Option<String>,
+    pub last_log_timeout: Option<u64>,
+    pub verbose: bool,
+}
+
+pub async fn handle_validate(args: ValidateArgs) -> Result<()> {
+    let ValidateArgs {
+        resource_pack,
+        behavior_pack,
+        only_warn,
+        fail_on_warn,
+        version,
+        locked,
+        world,
+        properties,
+        content_log_file,
+        last_log_timeout,
+        verbose,
+    } = args;
     let resource_path = Path::new(&resource_pack);
     let behavior_path = Path::new(&behavior_pack);
 
     if !resource_path.exists() {
-        anyhow::bail!("Resource pack not found at: {}", resource_pack);
+        return Err(
+            CliError::Usage(format!("Resource pack not found at: {}", resource_pack)).into(),
+        );
     }
     if !behavior_path.exists() {
-        anyhow::bail!("Behavior pack not found at: {}", behavior_pack);
+        return Err(
+            CliError::Usage(format!("Behavior pack not found at: {}", behavior_pack)).into(),
+        );
+    }
+    if let Some(world) = &world
+        && !Path::new(world).exists()
+    {
+        return Err(CliError::Usage(format!("World not found at: {}", world)).into());
     }
 
-    let version = match version {
-        Some(v) => v,
-        None => {
-            let versions = list_servers()?;
-            if versions.is_empty() {
-                anyhow::bail!("No server versions found. Please download a server version first.");
-            }
-            versions.last().unwrap().clone()
-        }
-    };
-
-    // Get server path from environment or use the specified version
-    let server_path = get_server_path(false)?.join(&version);
+    let version = resolve_server_version(version, locked)?;
 
-    if !server_path.exists() {
-        anyhow::bail!(
-            "Server version {} not found. Please download it first.",
-            version
-        );
-    }
+    println!(
+        "{}",
+        format!("Using server version: {}", version).cyan().bold()
+    );
 
-    println!("{}", format!("Using server version: {}", version).cyan().bold());
+    // Stop the server and unlink the packs if the job is cancelled, instead of leaving a server
+    // holding the port. The handlers are installed before the server starts, so an early signal
+    // can't kill the process halfway through
+    let mut signals = ShutdownSignals::register().context("Failed to install signal handlers")?;
+    let cancel = CancellationToken::new();
 
-    println!("{}", "Symlinking test packs to server directory...".cyan());
-    symlink_test_packs(&server_path, behavior_path, resource_path)?;
+    let validator = Validator::builder()
+        .server_version(&version)
+        .behavior_pack(behavior_path)
+        .resource_pack(resource_path)
+        .timeouts(Timeouts {
+            last_log: Duration::from_secs(last_log_timeout.unwrap_or(2)),
+            ..Default::default()
+        })
+        .properties(&properties)
+        .content_log_file(content_log_file.is_some())
+        .verbose(verbose)
+        .cancel_token(cancel.clone());
+    for (key, value) in properties.iter() {
+        println!("{}", format!("Overriding {}={}", key, value).cyan());
+    }
+    let validator = match world {
+        Some(world) => {
+            println!("{}", format!("Using world: {}", world).cyan());
+            validator.world(world)
+        }
+        None => validator,
+    };
+    let run = validator.run();
+    tokio::pin!(run);
+    let mut received = None;
+    let result = loop {
+        tokio::select! {
+            result = &mut run => break result,
+            signal = signals.recv(), if received.is_none() => {
+                eprintln!(
+                    "\n{}",
+                    format!("Received {}, stopping validation...", signal.name()).yellow()
+                );
+                received = Some(signal);
+                cancel.cancel();
+            }
+        }
+    };
 
-    println!("{}", "Starting server for validation...".cyan());
-    let validation_result = start_server(&server_path, last_log_timeout, verbose).await?;
+    let validation_result = match result {
+        Ok(report) => {
+            if let Some(path) = &content_log_file {
+                std::fs::write(
+                    path,
+                    report.result.content_log.as_deref().unwrap_or_default(),
+                )
+                .with_context(|| format!("Failed to save the content log to {}", path))?;
+                println!("{}", format!("Content log saved to: {}", path).cyan());
+            }
+            report.result
+        }
+        Err(ValidationError::ServerNotInstalled(version)) => {
+            return Err(CliError::Usage(format!(
+                "Server version {} not found. Please download it first.",
+                version
+            ))
+            .into());
+        }
+        Err(ValidationError::Cancelled) => {
+            eprintln!(
+                "{}",
+                "Validation interrupted, test packs unlinked.".yellow()
+            );
+            std::process::exit(received.map_or(EXIT_INTERRUPTED, |signal| signal.exit_code()));
+        }
+        Err(e) => {
+            // Errors logged before the failure often explain it, e.g. a script that crashed
+            if let Some(partial) = e.partial_result()
+                && !partial.errors.is_empty()
+            {
+                eprintln!("\n{}", "Errors logged before the failure:".red().bold());
+                for error in &partial.errors {
+                    eprintln!("  {}", error.red());
+                }
+            }
+            return Err(e.into());
+        }
+    };
 
     handle_validation_results(&validation_result, only_warn, fail_on_warn)
 }
@@ -63,6 +162,10 @@ fn handle_validation_results(
 ) -> Result<()> {
     println!("\n{}", "=== Validation Results ===".cyan().bold());
 
+    if let Some(version) = &validation_result.server_version {
+        println!("Server build: {}", version);
+    }
+
     if !validation_result.errors.is_empty() {
         println!("\n{}", "Errors:".red().bold());
         
@@ -144,17 +247,21 @@ fn handle_validation_results(
         Ok(())
     } else if fail_on_warn {
         if errors > 0 || warnings > 0 {
-            Err(anyhow::anyhow!(
+            Err(CliError::ValidationFailed(format!(
                 "Validation failed with {} errors and {} warnings (fail on warn mode)",
                 errors,
                 warnings
             ))
+            .into())
         } else {
             Ok(())
         }
     } else {
         if errors > 0 {
-            Err(anyhow::anyhow!("Validation failed with {} errors", errors))
+            Err(
+                CliError::ValidationFailed(format!("Validation failed with {} errors", errors))
+                    .into(),
+            )
         } else {
             Ok(())
         }
@@ -0,0 +1,54 @@
+use crate::error::CliError;
+use anyhow::Result;
+use bedrockci::integrity::verify_install;
+use bedrockci::server_path::get_server_path;
+use colored::*;
+
+/// Handles the verify command, checking an installed server against its integrity manifest.
+///
+/// Exits with status 1 if any installed file was modified or removed.
+pub async fn handle_verify(version: String) -> Result<()> {
+    let server_path = get_server_path(false)?.join(&version);
+
+    if !server_path.exists() {
+        return Err(CliError::Usage(format!(
+            "Server version {} not found. Please download it first using: bedrockci download --version {}",
+            version,
+            version
+        )).into());
+    }
+
+    println!(
+        "{}",
+        format!("Verifying server version: {}", version)
+            .cyan()
+            .bold()
+    );
+    let report = verify_install(&server_path)?;
+
+    for file in &report.modified {
+        println!("  {} {}", "modified:".red(), file);
+    }
+    for file in &report.missing {
+        println!("  {} {}", "missing:".red(), file);
+    }
+
+    if report.is_ok() {
+        println!(
+            "{}",
+            "✓ All installed files match the integrity manifest".green()
+        );
+        Ok(())
+    } else {
+        println!(
+            "{}",
+            format!(
+                "✗ {} modified and {} missing files",
+                report.modified.len(),
+                report.missing.len()
+            )
+            .red()
+        );
+        std::process::exit(1);
+    }
+}
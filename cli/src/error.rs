@@ -0,0 +1,42 @@
+use crate::commands::{EXIT_INFRASTRUCTURE, EXIT_INTERRUPTED, EXIT_USAGE, EXIT_VALIDATION_FAILED};
+use bedrockci::ErrorKind;
+
+/// A failure detected by the CLI itself, rather than reported by the library.
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    /// The command was used incorrectly, e.g. with a pack directory that doesn't exist.
+    #[error("{0}")]
+    Usage(String),
+    /// Validation ran, but found errors, or warnings in fail on warn mode.
+    #[error("{0}")]
+    ValidationFailed(String),
+}
+
+/// Returns the exit code for a command that failed with `error`.
+///
+/// The code is derived from the first CLI or library error in the error's chain. Anything else
+/// is treated as an infrastructure failure.
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    for cause in error.chain() {
+        match cause.downcast_ref::<CliError>() {
+            Some(CliError::Usage(_)) => return EXIT_USAGE,
+            Some(CliError::ValidationFailed(_)) => return EXIT_VALIDATION_FAILED,
+            None => {}
+        }
+        if let Some(kind) = ErrorKind::of(cause) {
+            return exit_code_for(kind);
+        }
+    }
+
+    EXIT_INFRASTRUCTURE
+}
+
+/// Returns the exit code for a library error of the given kind.
+pub fn exit_code_for(kind: ErrorKind) -> i32 {
+    match kind {
+        ErrorKind::Usage => EXIT_USAGE,
+        ErrorKind::Validation => EXIT_VALIDATION_FAILED,
+        ErrorKind::Infrastructure => EXIT_INFRASTRUCTURE,
+        ErrorKind::Cancelled => EXIT_INTERRUPTED,
+    }
+}
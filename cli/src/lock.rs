@@ -0,0 +1,61 @@
+use crate::error::CliError;
+use anyhow::{Context, Result};
+use bedrockci::lock::{LOCK_FILE_NAME, LockFile};
+use bedrockci::server::resolve_installed_version;
+use bedrockci::server_path::get_server_path;
+use std::path::Path;
+
+/// Resolves the installed server version to run, honouring `bedrockci.lock` on `--locked`.
+///
+/// With `locked`, the lock file of the current directory is required and the installed server
+/// must match it exactly. Otherwise the lock file is ignored and `--version` or the latest
+/// installed release is used.
+///
+/// # Arguments
+///
+/// * `version` - The `--version` given on the command line, if any
+/// * `locked` - Whether `--locked` was given
+///
+/// # Returns
+///
+/// * `Ok(String)` - The install directory name of the server to use
+/// * `Err(anyhow::Error)` - If no server is installed or it doesn't match the lock file
+pub fn resolve_server_version(version: Option<String>, locked: bool) -> Result<String> {
+    if locked {
+        let lock = LockFile::read(Path::new(LOCK_FILE_NAME))?.ok_or_else(|| {
+            CliError::Usage(format!(
+                "--locked requires a {} in the current directory",
+                LOCK_FILE_NAME
+            ))
+        })?;
+        lock.check_installed(&get_server_path(false)?)
+            .context("Run 'bedrockci download --locked' to install the locked server")?;
+        return Ok(lock.dir_name());
+    }
+
+    match resolve_installed_version(version.as_deref())? {
+        Some(version) => Ok(version),
+        None => Err(CliError::Usage(
+            "No server versions found. Please download a server version first using: bedrockci download"
+                .to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Fails if `name` is the server version pinned in the lock file of the current directory.
+///
+/// Guards commands that delete installed servers, which pass `--force` to skip the check.
+pub fn ensure_not_locked(name: &str) -> Result<()> {
+    if let Some(lock) = LockFile::read(Path::new(LOCK_FILE_NAME))?
+        && lock.dir_name() == name
+    {
+        return Err(CliError::Usage(format!(
+            "Server version {} is pinned in {}, use --force to remove it anyway",
+            name, LOCK_FILE_NAME
+        ))
+        .into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use crate::error::CliError;
+use anyhow::Result;
+use bedrockci::properties::{ServerProperties, parse_override};
+use std::path::Path;
+
+/// Collects the server.properties overrides given on the command line.
+///
+/// The values of `--properties-file` are applied first, so a `--property` for the same key wins.
+///
+/// # Arguments
+///
+/// * `file` - The `--properties-file` given on the command line, if any
+/// * `overrides` - Every `--property key=value` given on the command line
+///
+/// # Returns
+///
+/// * `Ok(ServerProperties)` - The properties to override, empty if none were given
+/// * `Err(anyhow::Error)` - If the file is missing or an override isn't `key=value`
+pub fn property_overrides(file: Option<&str>, overrides: &[String]) -> Result<ServerProperties> {
+    let mut properties = match file {
+        Some(file) if !Path::new(file).is_file() => {
+            return Err(CliError::Usage(format!("Properties file not found at: {}", file)).into());
+        }
+        Some(file) => ServerProperties::read(Path::new(file))?,
+        None => ServerProperties::default(),
+    };
+
+    for text in overrides {
+        let (key, value) = parse_override(text)?;
+        properties.set(&key, value);
+    }
+
+    Ok(properties)
+}
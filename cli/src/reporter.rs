@@ -0,0 +1,96 @@
+use bedrockci::report::{Event, LogLevel, Reporter, ServerPhase};
+use colored::*;
+use std::io::Write;
+
+/// Renders library events as colored terminal output.
+pub struct CliReporter {
+    /// Whether raw server output is shown, in which case info entries aren't repeated.
+    pub verbose: bool,
+    /// Whether lookup progress goes to stderr, for commands whose stdout is their result.
+    pub lookup_to_stderr: bool,
+}
+
+impl Reporter for CliReporter {
+    fn report(&self, event: &Event) {
+        match event {
+            Event::Status(message) => println!("{}", message),
+            Event::Warning(message) => eprintln!("Warning: {}", message),
+            Event::LookupProgress(message) if self.lookup_to_stderr => eprintln!("{}", message),
+            Event::LookupProgress(message) => println!("{}", message),
+            Event::LookupFallback(error) if self.lookup_to_stderr => {
+                eprintln!("{}, trying fallback...", error)
+            }
+            Event::LookupFallback(error) => println!("{}, trying fallback...", error),
+            Event::DownloadStarted { version, .. } => {
+                println!("Downloading Bedrock Server version {}...", version)
+            }
+            Event::DownloadResumed { offset } => {
+                println!("Resuming download from {} bytes...", offset)
+            }
+            Event::DownloadProgress { downloaded, total } => {
+                let percentage = (*downloaded as f64 / *total as f64 * 100.0) as u32;
+                print!("\rDownloading: {}%", percentage);
+                std::io::stdout().flush().ok();
+            }
+            Event::DownloadRetry {
+                error,
+                delay,
+                attempt,
+                max_attempts,
+            } => println!(
+                "\n{}, retrying in {}s (attempt {}/{})...",
+                error,
+                delay.as_secs_f32(),
+                attempt,
+                max_attempts
+            ),
+            Event::DownloadComplete => println!("\nDownload complete!"),
+            Event::InstallStarted { zip_path } => {
+                println!("Installing Bedrock Server from {}...", zip_path.display())
+            }
+            Event::ExtractionStarted => println!("Extracting server files..."),
+            Event::ExtractionProgress { extracted, total } => {
+                print!("\rExtracting: {}/{} files", extracted, total);
+                std::io::stdout().flush().ok();
+            }
+            Event::ExtractionComplete => println!("\nExtraction complete!"),
+            Event::VersionDetected(version) => println!("Server reports version {}", version),
+            Event::FilesShared { files, bytes } => println!(
+                "Shared {} files ({} bytes) with installed versions",
+                files, bytes
+            ),
+            Event::ServerPhase(phase) => report_phase(*phase),
+            Event::ServerLog(line) => println!("{}", line.dimmed()),
+            Event::LogEntry {
+                level: LogLevel::Info,
+                line,
+            } if !self.verbose => println!("{}", line.blue()),
+            _ => {}
+        }
+    }
+}
+
+fn report_phase(phase: ServerPhase) {
+    match phase {
+        ServerPhase::LinkingPacks => {
+            println!("{}", "Symlinking test packs to server directory...".cyan())
+        }
+        ServerPhase::Starting => {
+            println!("{}", "Starting server process...".cyan());
+            println!("{}", "Monitoring server output...".cyan());
+        }
+        ServerPhase::Started => println!("{}", "Server has started successfully".green()),
+        ServerPhase::Validating => println!("{}", "Starting validation...".cyan()),
+        ServerPhase::Idle(quiet) => println!(
+            "{}",
+            format!(
+                "\nNo new logs for {} seconds, validation complete.",
+                quiet.as_secs()
+            )
+            .yellow()
+        ),
+        ServerPhase::Stopping => println!("{}", "Stopping server...".cyan()),
+        ServerPhase::Stopped => println!("{}", "Server stopped.".green()),
+        _ => {}
+    }
+}
@@ -0,0 +1,61 @@
+use crate::commands::{EXIT_INTERRUPTED, EXIT_TERMINATED};
+use tokio::signal::unix::{Signal, SignalKind, signal};
+
+/// A signal asking the CLI to shut down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shutdown {
+    /// Ctrl+C (SIGINT).
+    Interrupt,
+    /// SIGTERM, as sent when a CI job is cancelled.
+    Terminate,
+}
+
+impl Shutdown {
+    /// Returns the name of the signal, for display.
+    pub fn name(self) -> &'static str {
+        match self {
+            Shutdown::Interrupt => "Ctrl+C",
+            Shutdown::Terminate => "SIGTERM",
+        }
+    }
+
+    /// Returns the exit code of a command stopped by this signal.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Shutdown::Interrupt => EXIT_INTERRUPTED,
+            Shutdown::Terminate => EXIT_TERMINATED,
+        }
+    }
+}
+
+/// Handlers for Ctrl+C (SIGINT) and SIGTERM.
+///
+/// Both handlers are installed by [`ShutdownSignals::register`], so a signal arriving before
+/// [`ShutdownSignals::recv`] is awaited is still received instead of killing the process.
+pub struct ShutdownSignals {
+    interrupt: Signal,
+    terminate: Signal,
+}
+
+impl ShutdownSignals {
+    /// Installs the handlers.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ShutdownSignals)` - The installed handlers
+    /// * `Err(std::io::Error)` - If a handler could not be installed
+    pub fn register() -> std::io::Result<Self> {
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    /// Waits for the next Ctrl+C or SIGTERM.
+    pub async fn recv(&mut self) -> Shutdown {
+        tokio::select! {
+            _ = self.interrupt.recv() => Shutdown::Interrupt,
+            _ = self.terminate.recv() => Shutdown::Terminate,
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::dedup::{STORE_DIR_NAME, dedup_install};
+use crate::error::{ErrorKind, display_chain};
+use crate::integrity::{InstallManifest, IntegrityError, MANIFEST_FILE_NAME, verify_install};
+use crate::report::{Event, report};
+use crate::server::{Channel, ServerError, install_staged};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the bundle layout changes, so old cache entries are never restored.
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("Server version {0} is not installed")]
+    VersionNotInstalled(String),
+    #[error("Server version {0} is already installed")]
+    AlreadyInstalled(String),
+    #[error("Integrity check failed: {0}")]
+    IntegrityCheckFailed(String),
+    #[error("Invalid cache bundle: {0}")]
+    InvalidBundle(String),
+    #[error("Failed to read cache bundle {}", path.display())]
+    BundleReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to write cache bundle {}", path.display())]
+    ArchiveFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to install cache bundle into {}", path.display())]
+    InstallFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+    #[error(transparent)]
+    Server(#[from] ServerError),
+}
+
+impl CacheError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CacheError::VersionNotInstalled(_)
+            | CacheError::AlreadyInstalled(_)
+            | CacheError::InvalidBundle(_)
+            | CacheError::BundleReadFailed { .. } => ErrorKind::Usage,
+            CacheError::IntegrityCheckFailed(_)
+            | CacheError::ArchiveFailed { .. }
+            | CacheError::InstallFailed { .. } => ErrorKind::Infrastructure,
+            CacheError::Integrity(e) => e.kind(),
+            CacheError::Server(e) => e.kind(),
+        }
+    }
+}
+
+/// Returns a stable cache key for an installed server directory name.
+///
+/// The key changes whenever the version, channel or bundle format changes, so it can be used
+/// directly as a CI cache key.
+///
+/// # Arguments
+///
+/// * `name` - The install directory name, e.g. `1.21.84.1` or `1.21.100.20-preview`
+pub fn cache_key(name: &str) -> String {
+    let (channel, version) = Channel::from_dir_name(name);
+    let channel = match channel {
+        Channel::Release => "release",
+        Channel::Preview => "preview",
+    };
+    format!(
+        "bedrockci-server-v{}-linux-{}-{}",
+        BUNDLE_FORMAT_VERSION, channel, version
+    )
+}
+
+/// Writes an installed server version to a `.tar.zst` cache bundle.
+///
+/// The install is verified against its integrity manifest first, so a bundle never captures a
+/// modified server. Only the files recorded in the manifest are archived, along with the manifest
+/// itself, so worlds, logs and linked test packs left in the install are never exported.
+///
+/// # Arguments
+///
+/// * `server_path` - The server path holding the installed versions
+/// * `name` - The install directory name to export
+/// * `bundle_path` - Where to write the bundle
+///
+/// # Returns
+///
+/// * `Ok(())` - If the bundle was written
+/// * `Err(CacheError)` - If the version is missing, fails verification or could not be archived
+pub fn export_server(server_path: &Path, name: &str, bundle_path: &Path) -> Result<(), CacheError> {
+    let version_path = server_path.join(name);
+    if name.starts_with('.') || !version_path.is_dir() {
+        return Err(CacheError::VersionNotInstalled(name.to_string()));
+    }
+    check_integrity(&version_path)?;
+
+    // Write next to the destination and rename, so an interrupted export leaves no bundle behind
+    let partial_path = partial_bundle_path(bundle_path);
+    write_bundle(&version_path, name, &partial_path)
+        .and_then(|_| {
+            fs::rename(&partial_path, bundle_path)
+                .map_err(|source| archive_failed(bundle_path, source))
+        })
+        .inspect_err(|_| {
+            fs::remove_file(&partial_path).ok();
+        })
+}
+
+/// Installs a server version from a `.tar.zst` cache bundle.
+///
+/// The bundle is unpacked into a hidden staging directory and verified against the integrity
+/// manifest it contains before being moved into place.
+///
+/// # Arguments
+///
+/// * `server_path` - The server path to install into
+/// * `bundle_path` - The bundle written by [`export_server`]
+/// * `force` - Replace the version if it is already installed
+///
+/// # Returns
+///
+/// * `Ok(String)` - The install directory name of the imported version
+/// * `Err(CacheError)` - If the bundle is invalid, fails verification or could not be installed
+pub fn import_server(
+    server_path: &Path,
+    bundle_path: &Path,
+    force: bool,
+) -> Result<String, CacheError> {
+    let bundle_read_failed = |source| CacheError::BundleReadFailed {
+        path: bundle_path.to_path_buf(),
+        source,
+    };
+    let decoder = File::open(bundle_path)
+        .and_then(|bundle| zstd::Decoder::new(BufReader::new(bundle)))
+        .map_err(bundle_read_failed)?;
+
+    let staging = tempfile::Builder::new()
+        .prefix(".import-")
+        .tempdir_in(server_path)
+        .map_err(|source| CacheError::InstallFailed {
+            path: server_path.to_path_buf(),
+            source,
+        })?;
+    let mut archive = tar::Archive::new(decoder);
+    archive.set_preserve_permissions(true);
+    archive.unpack(staging.path()).map_err(bundle_read_failed)?;
+
+    let name = bundled_version(staging.path())?;
+    let staged_path = staging.path().join(&name);
+    check_integrity(&staged_path)?;
+
+    let version_path = server_path.join(&name);
+    if version_path.exists() && !force {
+        return Err(CacheError::AlreadyInstalled(name));
+    }
+    install_staged(&staged_path, &version_path)?;
+
+    if let Err(e) = dedup_install(&version_path, &server_path.join(STORE_DIR_NAME)) {
+        report(Event::Warning(format!(
+            "Failed to deduplicate server files: {}",
+            display_chain(&e)
+        )));
+    }
+
+    Ok(name)
+}
+
+fn write_bundle(version_path: &Path, name: &str, bundle_path: &Path) -> Result<(), CacheError> {
+    let manifest = InstallManifest::read(version_path)?;
+    let encoder = File::create(bundle_path)
+        .and_then(|file| zstd::Encoder::new(BufWriter::new(file), zstd::DEFAULT_COMPRESSION_LEVEL))
+        .map_err(|source| archive_failed(bundle_path, source))?;
+
+    // Leaves out ownership and timestamps, so the same install always archives the same way
+    let mut builder = tar::Builder::new(encoder);
+    builder.mode(tar::HeaderMode::Deterministic);
+    builder.follow_symlinks(false);
+    manifest
+        .files
+        .keys()
+        .map(String::as_str)
+        .chain([MANIFEST_FILE_NAME])
+        .try_for_each(|relative_path| {
+            builder.append_path_with_name(
+                version_path.join(relative_path),
+                Path::new(name).join(relative_path),
+            )
+        })
+        .and_then(|_| builder.into_inner())
+        .and_then(|encoder| encoder.finish())
+        .and_then(|writer| writer.into_inner().map_err(|e| e.into_error()))
+        .and_then(|file| file.sync_all())
+        .map_err(|source| archive_failed(bundle_path, source))
+}
+
+fn archive_failed(path: &Path, source: std::io::Error) -> CacheError {
+    CacheError::ArchiveFailed {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Returns the single version directory a bundle unpacked into `staging_path`.
+fn bundled_version(staging_path: &Path) -> Result<String, CacheError> {
+    let entries: Vec<_> = fs::read_dir(staging_path)
+        .map_err(|source| CacheError::InstallFailed {
+            path: staging_path.to_path_buf(),
+            source,
+        })?
+        .filter_map(|entry| entry.ok())
+        .collect();
+
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                return Err(CacheError::InvalidBundle(format!(
+                    "unexpected directory {}",
+                    name
+                )));
+            }
+            Ok(name)
+        }
+        _ => Err(CacheError::InvalidBundle(
+            "expected exactly one server version directory".to_string(),
+        )),
+    }
+}
+
+fn check_integrity(version_path: &Path) -> Result<(), CacheError> {
+    let report = verify_install(version_path)?;
+    if !report.is_ok() {
+        let mut files = report.modified;
+        files.extend(report.missing);
+        return Err(CacheError::IntegrityCheckFailed(format!(
+            "modified or missing files: {}",
+            files.join(", ")
+        )));
+    }
+
+    // The manifest must describe this version, not one copied from elsewhere
+    let manifest = InstallManifest::read(version_path)?;
+    let name = version_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if Channel::from_dir_name(&name).1 != manifest.version {
+        return Err(CacheError::IntegrityCheckFailed(format!(
+            "manifest is for version {}, not {}",
+            manifest.version, name
+        )));
+    }
+
+    Ok(())
+}
+
+fn partial_bundle_path(bundle_path: &Path) -> PathBuf {
+    let mut name = bundle_path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::install_fixture;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(
+            cache_key("1.21.84.1"),
+            "bedrockci-server-v1-linux-release-1.21.84.1"
+        );
+        assert_eq!(
+            cache_key("1.21.100.20-preview"),
+            "bedrockci-server-v1-linux-preview-1.21.100.20"
+        );
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let bundle = source.path().join("server.tar.zst");
+        install_fixture(source.path(), "1.21.100.20-preview", "00");
+
+        export_server(source.path(), "1.21.100.20-preview", &bundle).unwrap();
+        let name = import_server(target.path(), &bundle, false).unwrap();
+
+        assert_eq!(name, "1.21.100.20-preview");
+        let version_path = target.path().join(&name);
+        assert!(verify_install(&version_path).unwrap().is_ok());
+        let mode = fs::metadata(version_path.join("bedrock_server"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert!(matches!(
+            import_server(target.path(), &bundle, false),
+            Err(CacheError::AlreadyInstalled(_))
+        ));
+        import_server(target.path(), &bundle, true).unwrap();
+    }
+
+    #[test]
+    fn test_export_leaves_out_runtime_state() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let bundle = source.path().join("server.tar.zst");
+        let version_path = install_fixture(source.path(), "1.21.84.1", "00");
+        export_server(source.path(), "1.21.84.1", &bundle).unwrap();
+        let clean_bundle = fs::read(&bundle).unwrap();
+
+        let world_path = version_path.join("worlds/Bedrock level");
+        fs::create_dir_all(&world_path).unwrap();
+        fs::write(world_path.join("level.dat"), b"level").unwrap();
+        fs::write(world_path.join("world_behavior_packs.json"), b"[]").unwrap();
+        fs::create_dir_all(version_path.join("behavior_packs")).unwrap();
+        std::os::unix::fs::symlink(
+            source.path(),
+            version_path.join("behavior_packs/TESTING_PACK_BP"),
+        )
+        .unwrap();
+
+        export_server(source.path(), "1.21.84.1", &bundle).unwrap();
+        assert_eq!(fs::read(&bundle).unwrap(), clean_bundle);
+
+        let name = import_server(target.path(), &bundle, false).unwrap();
+        let imported = target.path().join(name);
+        assert!(verify_install(&imported).unwrap().is_ok());
+        assert!(!imported.join("worlds").exists());
+        assert!(!imported.join("behavior_packs").exists());
+    }
+
+    #[test]
+    fn test_export_refuses_modified_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("server.tar.zst");
+        install_fixture(dir.path(), "1.21.84.1", "00");
+        fs::write(dir.path().join("1.21.84.1/bedrock_server"), b"patched").unwrap();
+
+        assert!(matches!(
+            export_server(dir.path(), "1.21.84.1", &bundle),
+            Err(CacheError::IntegrityCheckFailed(_))
+        ));
+        assert!(!bundle.exists());
+        assert!(!partial_bundle_path(&bundle).exists());
+    }
+
+    #[test]
+    fn test_import_rejects_tampered_bundle() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let bundle = source.path().join("server.tar.zst");
+        install_fixture(source.path(), "1.21.84.1", "00");
+        fs::write(source.path().join("1.21.84.1/bedrock_server"), b"patched").unwrap();
+        write_bundle(&source.path().join("1.21.84.1"), "1.21.84.1", &bundle).unwrap();
+
+        assert!(matches!(
+            import_server(target.path(), &bundle, false),
+            Err(CacheError::IntegrityCheckFailed(_))
+        ));
+        assert_eq!(fs::read_dir(target.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::error::ErrorKind;
+use crate::report::LogLevel;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Directory the server writes its content log files to, inside the server directory.
+pub const CONTENT_LOG_DIR: &str = "logs";
+
+/// Prefix of the content log file names, e.g. `ContentLog__Tue_Jun_17_2025__10_00_00.txt`.
+const CONTENT_LOG_PREFIX: &str = "ContentLog";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContentLogError {
+    #[error("Failed to read content log {}", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl ContentLogError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Infrastructure
+    }
+}
+
+/// A content log entry, as written to the console or the content log file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentLogEntry {
+    /// When the entry was logged, as written by the server.
+    pub time: Option<String>,
+    pub level: LogLevel,
+    /// The system that logged the entry, e.g. `Scripting` or `Json`.
+    pub category: Option<String>,
+    pub message: String,
+}
+
+impl ContentLogEntry {
+    /// Parses a line of the content log file, e.g.
+    /// `10:00:00[Scripting][error]-Plugin [TEST] - module not found`.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(ContentLogEntry)` - The parsed entry
+    /// * `None` - If the line isn't an error, warning or info entry
+    pub fn parse_file_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let start = line.find('[')?;
+        let time = line[..start].trim();
+
+        let mut tags = Vec::new();
+        let mut rest = &line[start..];
+        while let Some(tag) = rest.strip_prefix('[') {
+            let end = tag.find(']')?;
+            tags.push(&tag[..end]);
+            rest = &tag[end + 1..];
+        }
+        let (level_tag, tags) = tags.split_last()?;
+
+        Some(Self {
+            time: (!time.is_empty()).then(|| time.to_string()),
+            level: parse_level(level_tag)?,
+            category: tags.last().map(|tag| tag.to_string()),
+            message: rest.strip_prefix('-').unwrap_or(rest).trim().to_string(),
+        })
+    }
+
+    /// Parses a server console line, e.g.
+    /// `[2025-06-17 10:00:00:000 ERROR] [Scripting] Plugin [TEST] - module not found`.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(ContentLogEntry)` - The parsed entry
+    /// * `None` - If the line isn't an error, warning or info entry
+    pub fn parse_console_line(line: &str) -> Option<Self> {
+        let header = line.trim().strip_prefix('[')?;
+        let end = header.find(']')?;
+        let (time, level) = header[..end].rsplit_once(' ')?;
+        let rest = header[end + 1..].trim();
+
+        let (category, message) = match rest.strip_prefix('[').and_then(|tag| tag.split_once(']')) {
+            Some((category, message)) => (Some(category.to_string()), message.trim()),
+            None => (None, rest),
+        };
+
+        Some(Self {
+            time: Some(time.trim().to_string()),
+            level: parse_level(level)?,
+            category,
+            message: message.to_string(),
+        })
+    }
+
+    /// Formats the entry like a server console line, so it's reported the same way.
+    pub fn to_console_line(&self) -> String {
+        let level = match self.level {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warning => "WARN",
+            LogLevel::Info => "INFO",
+        };
+        let header = match &self.time {
+            Some(time) => format!("[{} {}]", time, level),
+            None => format!("[{}]", level),
+        };
+        match &self.category {
+            Some(category) => format!("{} [{}] {}", header, category, self.message),
+            None => format!("{} {}", header, self.message),
+        }
+    }
+
+    /// Returns whether `other` is the same entry, logged to another output.
+    pub fn same_entry(&self, other: &ContentLogEntry) -> bool {
+        self.level == other.level && self.message == other.message
+    }
+}
+
+fn parse_level(level: &str) -> Option<LogLevel> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "error" => Some(LogLevel::Error),
+        "warn" | "warning" => Some(LogLevel::Warning),
+        "info" => Some(LogLevel::Info),
+        _ => None,
+    }
+}
+
+/// Follows the content log files of a server, to read what was written to them during a run.
+///
+/// The server starts a new file for every run, but content already in a file when tailing
+/// started is skipped either way.
+#[derive(Debug)]
+pub struct ContentLogTail {
+    log_dir: PathBuf,
+    offsets: HashMap<PathBuf, u64>,
+}
+
+impl ContentLogTail {
+    /// Starts following the content log files of the server in `server_path`.
+    pub fn start(server_path: &Path) -> Self {
+        let log_dir = server_path.join(CONTENT_LOG_DIR);
+        let offsets = content_log_files(&log_dir)
+            .into_iter()
+            .filter_map(|path| {
+                let len = fs::metadata(&path).ok()?.len();
+                Some((path, len))
+            })
+            .collect();
+
+        Self { log_dir, offsets }
+    }
+
+    /// Reads everything written to the content log files since the last read.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The new content, in file name order, empty if nothing was written
+    /// * `Err(ContentLogError)` - If a content log file couldn't be read
+    pub fn read_new(&mut self) -> Result<String, ContentLogError> {
+        let mut content = String::new();
+        for path in content_log_files(&self.log_dir) {
+            let read_failed = |source| ContentLogError::ReadFailed {
+                path: path.clone(),
+                source,
+            };
+            let mut file = File::open(&path).map_err(read_failed)?;
+            let len = file.metadata().map_err(read_failed)?.len();
+            // A file shorter than before was started over
+            let offset = match self.offsets.get(&path) {
+                Some(&offset) if offset <= len => offset,
+                _ => 0,
+            };
+
+            let mut bytes = Vec::new();
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_to_end(&mut bytes))
+                .map_err(read_failed)?;
+            self.offsets
+                .insert(path.clone(), offset + bytes.len() as u64);
+            content.push_str(&String::from_utf8_lossy(&bytes));
+        }
+
+        Ok(content)
+    }
+}
+
+/// Returns the content log files in `log_dir`, sorted by name.
+fn content_log_files(log_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(log_dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(CONTENT_LOG_PREFIX)
+                && entry.path().is_file()
+        })
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_and_console_lines_parse_to_the_same_entry() {
+        let file = ContentLogEntry::parse_file_line(
+            "10:00:00[Scripting][error]-Plugin [TEST] - module not found",
+        )
+        .unwrap();
+        let console = ContentLogEntry::parse_console_line(
+            "[2025-06-17 10:00:00:000 ERROR] [Scripting] Plugin [TEST] - module not found",
+        )
+        .unwrap();
+
+        assert_eq!(file.time.as_deref(), Some("10:00:00"));
+        assert_eq!(file.category.as_deref(), Some("Scripting"));
+        assert_eq!(file.message, "Plugin [TEST] - module not found");
+        assert!(file.same_entry(&console));
+        assert_eq!(
+            file.to_console_line(),
+            "[10:00:00 ERROR] [Scripting] Plugin [TEST] - module not found"
+        );
+
+        let warning =
+            ContentLogEntry::parse_file_line("10:00:01[Json][warning]-Unknown field").unwrap();
+        assert_eq!(warning.level, LogLevel::Warning);
+        assert!(!warning.same_entry(&console));
+        assert!(ContentLogEntry::parse_file_line("Content log started").is_none());
+        assert!(ContentLogEntry::parse_console_line("NO LOG FILE! - setting up").is_none());
+    }
+
+    #[test]
+    fn test_tail_reads_only_new_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = dir.path().join(CONTENT_LOG_DIR);
+        fs::create_dir_all(&log_dir).unwrap();
+        let old = log_dir.join("ContentLog__old.txt");
+        fs::write(&old, "09:00:00[Json][error]-From an earlier run\n").unwrap();
+        fs::write(log_dir.join("Debug_Log.txt"), "unrelated\n").unwrap();
+
+        let mut tail = ContentLogTail::start(dir.path());
+        assert_eq!(tail.read_new().unwrap(), "");
+
+        let mut appended = fs::read_to_string(&old).unwrap();
+        appended.push_str("10:00:00[Json][warning]-Appended\n");
+        fs::write(&old, appended).unwrap();
+        fs::write(
+            log_dir.join("ContentLog__new.txt"),
+            "10:00:01[Scripting][error]-New file\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            tail.read_new().unwrap(),
+            "10:00:01[Scripting][error]-New file\n10:00:00[Json][warning]-Appended\n"
+        );
+        assert_eq!(tail.read_new().unwrap(), "");
+    }
+}
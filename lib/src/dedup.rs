@@ -0,0 +1,432 @@
+use crate::error::ErrorKind;
+use crate::integrity::{InstallManifest, IntegrityError, MANIFEST_FILE_NAME, hash_file};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Name of the content-addressed store shared by all installed versions, inside the server path.
+pub const STORE_DIR_NAME: &str = ".store";
+
+/// Names of files the server or BedrockCI edit in place, wherever they are in the install.
+const EDITABLE_FILE_NAMES: [&str; 2] = ["allowlist.json", "permissions.json"];
+
+/// Directory of configuration files that are edited in place, relative to the install.
+const EDITABLE_DIR: &str = "config";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DedupError {
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+    #[error("Failed to read {}", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to link {}", path.display())]
+    LinkFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl DedupError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            DedupError::Integrity(e) => e.kind(),
+            _ => ErrorKind::Infrastructure,
+        }
+    }
+}
+
+/// The outcome of deduplicating one or more installed versions.
+#[derive(Debug, Default)]
+pub struct DedupStats {
+    /// Files replaced by a hardlink to an identical file already in the store.
+    pub linked_files: usize,
+    /// Bytes no longer stored twice because of those links.
+    pub bytes_saved: u64,
+}
+
+/// The outcome of [`gc`].
+#[derive(Debug, Default)]
+pub struct GcReport {
+    /// Files newly shared between installed versions.
+    pub dedup: DedupStats,
+    /// Store objects removed because no installed version used them any more.
+    pub removed_objects: usize,
+    /// Bytes freed by removing those objects.
+    pub bytes_freed: u64,
+}
+
+/// Replaces the files of an installed version with hardlinks into the shared store.
+///
+/// Files whose contents and permissions match a file of another installed version end up
+/// sharing one copy on disk. Files not yet in the store are added to it.
+///
+/// Only files recorded in the install manifest and unchanged since install are linked. Anything
+/// else, such as worlds, logs and world pack configurations, is written to in place and must
+/// stay private to this version.
+///
+/// # Arguments
+///
+/// * `version_path` - The installed version directory
+/// * `store_path` - The content-addressed store, created if missing
+///
+/// # Returns
+///
+/// * `Ok(DedupStats)` - How many files are now shared with other versions
+/// * `Err(DedupError)` - If the manifest is missing, or the files could not be hashed or linked
+pub fn dedup_install(version_path: &Path, store_path: &Path) -> Result<DedupStats, DedupError> {
+    let manifest = InstallManifest::read(version_path)?;
+    let mut files = BTreeMap::new();
+    for (relative_path, hash) in manifest.files {
+        let path = version_path.join(&relative_path);
+        if path.is_file() && hash_file(&path)? == hash {
+            files.insert(relative_path, hash);
+        }
+    }
+    link_into_store(version_path, &files, store_path)
+}
+
+/// Links already hashed files of `version_path` into the store, see [`dedup_install`].
+///
+/// `files` maps paths relative to `version_path` to their SHA-256, as recorded in an
+/// [`InstallManifest`](crate::integrity::InstallManifest) just generated for it.
+pub(crate) fn link_into_store(
+    version_path: &Path,
+    files: &BTreeMap<String, String>,
+    store_path: &Path,
+) -> Result<DedupStats, DedupError> {
+    fs::create_dir_all(store_path).map_err(|source| link_failed(store_path, source))?;
+
+    let mut stats = DedupStats::default();
+    for (relative_path, hash) in files {
+        if relative_path == MANIFEST_FILE_NAME || is_editable(relative_path) {
+            continue;
+        }
+
+        let path = version_path.join(relative_path);
+        let metadata = fs::symlink_metadata(&path).map_err(|source| read_failed(&path, source))?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        // Permissions are shared between hardlinks, so they are part of the object's identity
+        let object = store_path.join(format!(
+            "{}-{:o}",
+            hash,
+            metadata.permissions().mode() & 0o7777
+        ));
+        match fs::metadata(&object) {
+            Ok(existing) => {
+                let already_linked =
+                    existing.ino() == metadata.ino() && existing.dev() == metadata.dev();
+                if already_linked || existing.len() != metadata.len() {
+                    continue;
+                }
+                // A linked file edited in place changes the object too, so never trust its name
+                if hash_file(&object)? != *hash {
+                    fs::remove_file(&object)
+                        .and_then(|_| fs::hard_link(&path, &object))
+                        .map_err(|source| link_failed(&path, source))?;
+                    continue;
+                }
+                replace_with_link(&object, &path)?;
+                stats.linked_files += 1;
+                stats.bytes_saved += metadata.len();
+            }
+            Err(_) => {
+                fs::hard_link(&path, &object).map_err(|source| link_failed(&path, source))?;
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Removes store objects that are no longer linked from any installed version.
+///
+/// # Returns
+///
+/// * `Ok((usize, u64))` - The number of objects removed and the bytes freed
+/// * `Err(DedupError)` - If the store could not be read
+pub fn sweep_store(store_path: &Path) -> Result<(usize, u64), DedupError> {
+    let mut removed = 0;
+    let mut freed = 0;
+    if !store_path.exists() {
+        return Ok((removed, freed));
+    }
+
+    let entries = fs::read_dir(store_path).map_err(|source| read_failed(store_path, source))?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        // The store's own link is the only one left
+        if metadata.is_file() && metadata.nlink() == 1 && fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+            freed += metadata.len();
+        }
+    }
+
+    Ok((removed, freed))
+}
+
+/// Deduplicates every installed version under `server_path` and removes unused store objects.
+///
+/// Useful for versions installed before deduplication was applied at download time, and after
+/// removing versions by hand. Versions without an install manifest are skipped, since their
+/// installed files can't be told apart from runtime state.
+///
+/// # Arguments
+///
+/// * `server_path` - The server path holding the installed versions
+///
+/// # Returns
+///
+/// * `Ok(GcReport)` - The space shared and freed
+/// * `Err(DedupError)` - If an installed version could not be deduplicated
+pub fn gc(server_path: &Path) -> Result<GcReport, DedupError> {
+    let store_path = server_path.join(STORE_DIR_NAME);
+    let mut report = GcReport::default();
+
+    let entries = fs::read_dir(server_path).map_err(|source| read_failed(server_path, source))?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if is_hidden || !entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            continue;
+        }
+
+        let stats = match dedup_install(&entry.path(), &store_path) {
+            Ok(stats) => stats,
+            Err(DedupError::Integrity(IntegrityError::ManifestMissing(_))) => continue,
+            Err(e) => return Err(e),
+        };
+        report.dedup.linked_files += stats.linked_files;
+        report.dedup.bytes_saved += stats.bytes_saved;
+    }
+
+    (report.removed_objects, report.bytes_freed) = sweep_store(&store_path)?;
+    Ok(report)
+}
+
+/// Returns whether the server or BedrockCI may edit a file in place, so it must never be linked.
+///
+/// These are `*.properties` files, anything under `config/`, and `allowlist.json` or
+/// `permissions.json` in any directory.
+fn is_editable(relative_path: &str) -> bool {
+    let path = Path::new(relative_path);
+    path.starts_with(EDITABLE_DIR)
+        || path
+            .extension()
+            .is_some_and(|extension| extension == "properties")
+        || path
+            .file_name()
+            .is_some_and(|name| EDITABLE_FILE_NAMES.iter().any(|editable| name == *editable))
+}
+
+/// Atomically replaces `path` with a hardlink to `object`.
+fn replace_with_link(object: &Path, path: &Path) -> Result<(), DedupError> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.dedup", name));
+
+    fs::remove_file(&temp_path).ok();
+    fs::hard_link(object, &temp_path)
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|source| {
+            fs::remove_file(&temp_path).ok();
+            link_failed(path, source)
+        })
+}
+
+fn read_failed(path: &Path, source: std::io::Error) -> DedupError {
+    DedupError::ReadFailed {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn link_failed(path: &Path, source: std::io::Error) -> DedupError {
+    DedupError::LinkFailed {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes an installed version holding `files`, with an install manifest recording them.
+    fn write_version(server_path: &Path, name: &str, files: &[(&str, &[u8])]) {
+        write_files(server_path, name, files);
+        let version_path = server_path.join(name);
+        InstallManifest::generate(&version_path, name, "https://example.com/server.zip", "00")
+            .unwrap()
+            .write(&version_path)
+            .unwrap();
+    }
+
+    fn write_files(server_path: &Path, name: &str, files: &[(&str, &[u8])]) {
+        for (relative_path, contents) in files {
+            let path = server_path.join(name).join(relative_path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    fn inode(path: &Path) -> u64 {
+        fs::metadata(path).unwrap().ino()
+    }
+
+    #[test]
+    fn test_identical_files_are_linked() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: &[(&str, &[u8])] = &[
+            ("bedrock_server", b"binary"),
+            ("definitions/biomes.json", b"{}"),
+            ("server.properties", b"server-name=test\n"),
+        ];
+        write_version(dir.path(), "1.21.84.1", files);
+        write_version(dir.path(), "1.21.84.2", files);
+        fs::write(dir.path().join("1.21.84.2/bedrock_server"), b"patched").unwrap();
+
+        let report = gc(dir.path()).unwrap();
+
+        assert_eq!(report.dedup.linked_files, 1);
+        assert_eq!(report.dedup.bytes_saved, 2);
+        assert_eq!(
+            inode(&dir.path().join("1.21.84.1/definitions/biomes.json")),
+            inode(&dir.path().join("1.21.84.2/definitions/biomes.json"))
+        );
+        assert_ne!(
+            inode(&dir.path().join("1.21.84.1/bedrock_server")),
+            inode(&dir.path().join("1.21.84.2/bedrock_server"))
+        );
+        assert_ne!(
+            inode(&dir.path().join("1.21.84.1/server.properties")),
+            inode(&dir.path().join("1.21.84.2/server.properties"))
+        );
+    }
+
+    #[test]
+    fn test_editable_files_are_not_linked() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: &[(&str, &[u8])] = &[
+            ("config/default/permissions.json", b"{}"),
+            ("config/default/other.json", b"[]"),
+            ("packs/allowlist.json", b"[]"),
+            ("world.properties", b"a=b\n"),
+        ];
+        write_version(dir.path(), "1.21.84.1", files);
+        write_version(dir.path(), "1.21.84.2", files);
+
+        assert_eq!(gc(dir.path()).unwrap().dedup.linked_files, 0);
+        for (relative_path, _) in files {
+            assert!(is_editable(relative_path));
+            assert_ne!(
+                inode(&dir.path().join("1.21.84.1").join(relative_path)),
+                inode(&dir.path().join("1.21.84.2").join(relative_path))
+            );
+        }
+        assert!(!is_editable("definitions/config.json"));
+    }
+
+    #[test]
+    fn test_corrupt_store_object_is_replaced_not_linked() {
+        let dir = tempfile::tempdir().unwrap();
+        write_version(dir.path(), "1.21.84.1", &[("bedrock_server", b"binary")]);
+        gc(dir.path()).unwrap();
+        // Same length, different contents, as after editing a linked file in place
+        fs::write(dir.path().join("1.21.84.1/bedrock_server"), b"BINARY").unwrap();
+        write_version(dir.path(), "1.21.84.2", &[("bedrock_server", b"binary")]);
+
+        assert_eq!(gc(dir.path()).unwrap().dedup.linked_files, 0);
+        assert_eq!(
+            fs::read(dir.path().join("1.21.84.2/bedrock_server")).unwrap(),
+            b"binary"
+        );
+        write_version(dir.path(), "1.21.84.3", &[("bedrock_server", b"binary")]);
+        assert_eq!(gc(dir.path()).unwrap().dedup.linked_files, 1);
+        assert_eq!(
+            inode(&dir.path().join("1.21.84.2/bedrock_server")),
+            inode(&dir.path().join("1.21.84.3/bedrock_server"))
+        );
+    }
+
+    #[test]
+    fn test_files_with_different_permissions_are_not_linked() {
+        let dir = tempfile::tempdir().unwrap();
+        write_version(dir.path(), "1.21.84.1", &[("bedrock_server", b"binary")]);
+        write_version(dir.path(), "1.21.84.2", &[("bedrock_server", b"binary")]);
+        let path = dir.path().join("1.21.84.2/bedrock_server");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(gc(dir.path()).unwrap().dedup.linked_files, 0);
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+    }
+
+    #[test]
+    fn test_runtime_files_are_not_linked() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime: &[(&str, &[u8])] = &[
+            ("worlds/Bedrock level/world_behavior_packs.json", b"[]"),
+            ("worlds/Bedrock level/level.dat", b"level"),
+            ("logs/ContentLog__run.txt", b"log"),
+        ];
+        for name in ["1.21.84.1", "1.21.84.2"] {
+            write_version(dir.path(), name, &[("bedrock_server", b"binary")]);
+            write_files(dir.path(), name, runtime);
+        }
+        // A version without a manifest can't be told apart from runtime state at all
+        write_files(dir.path(), "1.21.84.3", &[("bedrock_server", b"binary")]);
+
+        assert_eq!(gc(dir.path()).unwrap().dedup.linked_files, 1);
+        let world_packs = "worlds/Bedrock level/world_behavior_packs.json";
+        fs::write(dir.path().join("1.21.84.1").join(world_packs), b"[{}]").unwrap();
+
+        assert_eq!(
+            fs::read(dir.path().join("1.21.84.2").join(world_packs)).unwrap(),
+            b"[]"
+        );
+        for (relative_path, _) in runtime {
+            assert_ne!(
+                inode(&dir.path().join("1.21.84.1").join(relative_path)),
+                inode(&dir.path().join("1.21.84.2").join(relative_path))
+            );
+        }
+        assert_ne!(
+            inode(&dir.path().join("1.21.84.1/bedrock_server")),
+            inode(&dir.path().join("1.21.84.3/bedrock_server"))
+        );
+    }
+
+    #[test]
+    fn test_gc_removes_unused_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        write_version(dir.path(), "1.21.84.1", &[("bedrock_server", b"binary")]);
+        gc(dir.path()).unwrap();
+
+        fs::remove_dir_all(dir.path().join("1.21.84.1")).unwrap();
+        let report = gc(dir.path()).unwrap();
+
+        assert_eq!(report.removed_objects, 1);
+        assert_eq!(report.bytes_freed, 6);
+        assert_eq!(
+            fs::read_dir(dir.path().join(STORE_DIR_NAME))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+}
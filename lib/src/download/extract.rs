@@ -0,0 +1,281 @@
+use crate::download::server::ServerDownloadError;
+use crate::report::{Event, report};
+use std::fs::{self, File, TryLockError};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tempfile::TempDir;
+use zip::ZipArchive;
+
+/// Separates a staging directory's name from the name of a copy made to detect its version.
+pub(crate) const DETECT_COPY_INFIX: &str = ".detect-";
+
+/// A staging directory, locked for as long as it is in use.
+///
+/// Concurrent installs of the same version share a name prefix, so the lock is what tells a
+/// staging directory in use apart from one left behind by an interrupted install.
+pub(crate) struct StagingDir {
+    dir: TempDir,
+    _lock: File,
+}
+
+impl StagingDir {
+    /// Returns the path of the staging directory.
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Creates an empty staging directory in `download_path` to extract a server into.
+///
+/// Leftover staging directories from interrupted installs of the same version are removed first,
+/// while those of installs still running are left alone. The staging directory is hidden, so it
+/// is never reported by [`list_servers`](crate::server::list_servers), and is deleted on drop
+/// unless moved into place.
+pub(crate) fn create_staging_dir(
+    download_path: &Path,
+    version: &str,
+) -> Result<StagingDir, ServerDownloadError> {
+    let prefix = staging_prefix(version);
+    remove_stale_staging_dirs(download_path, &prefix);
+
+    let creation_failed = |source| ServerDownloadError::TempFileCreationFailed {
+        path: download_path.to_path_buf(),
+        source,
+    };
+    loop {
+        let dir = tempfile::Builder::new()
+            .prefix(&prefix)
+            .tempdir_in(download_path)
+            .map_err(creation_failed)?;
+        match try_lock_dir(dir.path()) {
+            Ok(Some(lock)) if dir.path().exists() => return Ok(StagingDir { dir, _lock: lock }),
+            // Another install removed it as stale before it was locked, so make a new one
+            Ok(_) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(source) => return Err(creation_failed(source)),
+        }
+    }
+}
+
+/// Removes the staging directories starting with `prefix` whose install is no longer running,
+/// along with the copies made from them to detect their version.
+fn remove_stale_staging_dirs(download_path: &Path, prefix: &str) {
+    let Ok(entries) = fs::read_dir(download_path) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(prefix) {
+            continue;
+        }
+
+        let owner = name.split(DETECT_COPY_INFIX).next().unwrap_or(&name);
+        match try_lock_dir(&download_path.join(owner)) {
+            // Holding the lock while removing keeps a new install from reusing it meanwhile
+            Ok(Some(_lock)) => {
+                fs::remove_dir_all(entry.path()).ok();
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                fs::remove_dir_all(entry.path()).ok();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Locks the directory at `path`, returning `None` if another process holds the lock.
+fn try_lock_dir(path: &Path) -> std::io::Result<Option<File>> {
+    let dir = File::open(path)?;
+    match dir.try_lock() {
+        Ok(()) => Ok(Some(dir)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Extracts every entry of the zip at `zip_path` into `dest`.
+///
+/// Entry names that would escape `dest` are rejected, and stored Unix permissions are applied
+/// to the extracted files. Directory permissions are applied last, once their children exist.
+/// Any unreadable entry fails the whole extraction.
+pub(crate) fn extract_archive(zip_path: &Path, dest: &Path) -> Result<(), ServerDownloadError> {
+    let zip_file = File::open(zip_path).map_err(|source| ServerDownloadError::ZipReadFailed {
+        path: zip_path.to_path_buf(),
+        source,
+    })?;
+    let zip_invalid = |source| ServerDownloadError::ZipInvalid {
+        path: zip_path.to_path_buf(),
+        source,
+    };
+    let mut archive = ZipArchive::new(zip_file).map_err(zip_invalid)?;
+
+    let total_files = archive.len();
+    let mut directory_modes = Vec::new();
+    for i in 0..total_files {
+        let mut file = archive.by_index(i).map_err(zip_invalid)?;
+        let relative_path = file
+            .enclosed_name()
+            .ok_or_else(|| ServerDownloadError::UnsafeZipEntry(file.name().to_string()))?;
+        let outpath = dest.join(relative_path);
+
+        if file.is_dir() {
+            fs::create_dir_all(&outpath).map_err(|source| extraction_failed(&outpath, source))?;
+        } else {
+            if let Some(parent) = outpath.parent() {
+                fs::create_dir_all(parent).map_err(|source| extraction_failed(parent, source))?;
+            }
+            File::create(&outpath)
+                .and_then(|mut outfile| std::io::copy(&mut file, &mut outfile))
+                .map_err(|source| extraction_failed(&outpath, source))?;
+        }
+
+        if let Some(mode) = file.unix_mode() {
+            if file.is_dir() {
+                // A read-only directory mode would block extracting its children, so wait
+                directory_modes.push((outpath, mode));
+            } else {
+                set_mode(&outpath, mode)?;
+            }
+        }
+
+        report(Event::ExtractionProgress {
+            extracted: i + 1,
+            total: total_files,
+        });
+    }
+
+    // Children first, so restricting a parent never blocks changing a nested directory
+    for (path, mode) in directory_modes.iter().rev() {
+        set_mode(path, *mode)?;
+    }
+
+    Ok(())
+}
+
+fn set_mode(path: &Path, mode: u32) -> Result<(), ServerDownloadError> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+        .map_err(|source| extraction_failed(path, source))
+}
+
+fn extraction_failed(path: &Path, source: std::io::Error) -> ServerDownloadError {
+    ServerDownloadError::ExtractionFailed {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn staging_prefix(version: &str) -> String {
+    format!(".{}.extract-", version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::test_support::write_zip;
+
+    #[test]
+    fn test_extract_preserves_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("server.zip");
+        write_zip(
+            &zip_path,
+            &[
+                ("bedrock_server", b"binary", 0o755),
+                ("definitions/biomes.json", b"{}", 0o644),
+            ],
+        );
+
+        let dest = dir.path().join("out");
+        extract_archive(&zip_path, &dest).unwrap();
+
+        let mode = fs::metadata(dest.join("bedrock_server"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(
+            fs::read(dest.join("definitions/biomes.json")).unwrap(),
+            b"{}"
+        );
+    }
+
+    #[test]
+    fn test_extract_applies_directory_modes_after_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("server.zip");
+        write_zip(
+            &zip_path,
+            &[
+                ("definitions/", b"", 0o555),
+                ("definitions/biomes.json", b"{}", 0o644),
+            ],
+        );
+
+        let dest = dir.path().join("out");
+        extract_archive(&zip_path, &dest).unwrap();
+
+        let definitions = dest.join("definitions");
+        let mode = fs::metadata(&definitions).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o555);
+        assert_eq!(fs::read(definitions.join("biomes.json")).unwrap(), b"{}");
+        fs::set_permissions(&definitions, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_extract_rejects_path_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("server.zip");
+        write_zip(&zip_path, &[("../escaped.txt", b"evil", 0o644)]);
+
+        let dest = dir.path().join("out");
+        let result = extract_archive(&zip_path, &dest);
+
+        assert!(matches!(
+            result,
+            Err(ServerDownloadError::UnsafeZipEntry(_))
+        ));
+        assert!(!dir.path().join("escaped.txt").exists());
+    }
+
+    #[test]
+    fn test_dropped_staging_dir_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = create_staging_dir(dir.path(), "1.21.84.1").unwrap();
+        fs::write(staging.path().join("partial.txt"), b"partial").unwrap();
+        drop(staging);
+
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_stale_staging_dirs_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let stale = dir.path().join(".1.21.84.1.extract-stale");
+        fs::create_dir_all(&stale).unwrap();
+
+        let _staging = create_staging_dir(dir.path(), "1.21.84.1").unwrap();
+
+        assert!(!stale.exists());
+    }
+
+    #[test]
+    fn test_staging_dirs_in_use_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = create_staging_dir(dir.path(), "unknown").unwrap();
+        let mut detect_copy = first.path().as_os_str().to_owned();
+        detect_copy.push(format!("{}copy", DETECT_COPY_INFIX));
+        fs::create_dir_all(&detect_copy).unwrap();
+        let stale_copy = dir
+            .path()
+            .join(format!(".unknown.extract-gone{}copy", DETECT_COPY_INFIX));
+        fs::create_dir_all(&stale_copy).unwrap();
+
+        let second = create_staging_dir(dir.path(), "unknown").unwrap();
+
+        assert!(first.path().exists());
+        assert!(Path::new(&detect_copy).exists());
+        assert!(second.path().exists());
+        assert!(!stale_copy.exists());
+    }
+}
@@ -0,0 +1,640 @@
+use crate::download::network::NetworkConfig;
+use crate::error::display_chain;
+use crate::report::{Event, report};
+use futures::StreamExt;
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::{Certificate, NoProxy, Proxy, StatusCode};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Why a download failed, after retrying transient failures.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("{url} returned {status}")]
+    Status { url: String, status: StatusCode },
+    #[error("Request to {url} failed")]
+    Request {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("Download from {url} ended early at {downloaded} of {expected} bytes")]
+    Incomplete {
+        url: String,
+        downloaded: u64,
+        expected: u64,
+    },
+    #[error("{url} can't resume the partial download")]
+    ResumeRejected { url: String },
+    #[error("Failed to write download file {}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Gave up after {attempts} attempts")]
+    RetriesExhausted {
+        attempts: u32,
+        #[source]
+        source: Box<FetchError>,
+    },
+    // The URL may contain credentials, so it is left out of the message
+    #[error("Invalid proxy URL")]
+    InvalidProxy(#[source] reqwest::Error),
+    #[error("Failed to read CA bundle {}", path.display())]
+    CaBundleReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("No PEM certificates found in CA bundle {}", path.display())]
+    CaBundleInvalid {
+        path: PathBuf,
+        #[source]
+        source: Option<reqwest::Error>,
+    },
+    #[error("Failed to create HTTP client")]
+    ClientFailed(#[source] reqwest::Error),
+}
+
+/// Controls how often and how quickly failed downloads are retried.
+///
+/// Connection errors, dropped transfers, timeouts and 5xx/429 responses are retried with
+/// exponential backoff. Other client errors fail immediately.
+#[derive(Debug, Clone)]
+pub(crate) struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry. Doubled for every following retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay to wait after the given (1-based) failed attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+enum AttemptError {
+    /// Worth retrying, e.g. a dropped connection or a 503.
+    Transient(FetchError),
+    /// Retrying will not help, e.g. a 404 or a local I/O failure.
+    Fatal(FetchError),
+}
+
+/// Builds the HTTP client used for downloads and latest-version lookups.
+///
+/// An explicit proxy in `config` replaces the proxies from the environment, and the CA bundle is
+/// trusted in addition to the system root certificates.
+pub(crate) fn http_client(config: &NetworkConfig) -> Result<reqwest::Client, FetchError> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT);
+
+    if let Some(proxy_url) = &config.proxy {
+        let no_proxy = match &config.no_proxy {
+            Some(hosts) => NoProxy::from_string(hosts),
+            None => NoProxy::from_env(),
+        };
+        let proxy = Proxy::all(proxy_url)
+            .map_err(FetchError::InvalidProxy)?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ca_bundle) = &config.ca_bundle {
+        let pem = std::fs::read(ca_bundle).map_err(|source| FetchError::CaBundleReadFailed {
+            path: ca_bundle.clone(),
+            source,
+        })?;
+        let certificates =
+            Certificate::from_pem_bundle(&pem).map_err(|source| FetchError::CaBundleInvalid {
+                path: ca_bundle.clone(),
+                source: Some(source),
+            })?;
+        if certificates.is_empty() {
+            return Err(FetchError::CaBundleInvalid {
+                path: ca_bundle.clone(),
+                source: None,
+            });
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder.build().map_err(FetchError::ClientFailed)
+}
+
+/// Streams `url` to `dest`, resuming from any partial content already in `dest`.
+///
+/// Transient failures are retried according to `policy`. On failure the partial file is left in
+/// place so a later call can resume it.
+pub(crate) async fn fetch_to_file(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    policy: &RetryPolicy,
+) -> Result<(), FetchError> {
+    let mut attempt = 1;
+    loop {
+        match fetch_attempt(client, url, dest).await {
+            Ok(()) => return Ok(()),
+            Err(AttemptError::Fatal(e)) => return Err(e),
+            Err(AttemptError::Transient(e)) => {
+                if attempt >= policy.max_attempts {
+                    return Err(FetchError::RetriesExhausted {
+                        attempts: attempt,
+                        source: Box::new(e),
+                    });
+                }
+                let delay = policy.backoff(attempt);
+                report(Event::DownloadRetry {
+                    error: display_chain(&e),
+                    delay,
+                    attempt: attempt + 1,
+                    max_attempts: policy.max_attempts,
+                });
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+async fn fetch_attempt(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+) -> Result<(), AttemptError> {
+    let resume_from = tokio::fs::metadata(dest)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+
+    let mut request = client.get(url);
+    if resume_from > 0 {
+        request = request.header(RANGE, format!("bytes={}-", resume_from));
+    }
+
+    let request_failed = |source| {
+        AttemptError::Transient(FetchError::Request {
+            url: url.to_string(),
+            source,
+        })
+    };
+    let write_failed = |source| {
+        AttemptError::Fatal(FetchError::Write {
+            path: dest.to_path_buf(),
+            source,
+        })
+    };
+    let resume_rejected = || {
+        AttemptError::Transient(FetchError::ResumeRejected {
+            url: url.to_string(),
+        })
+    };
+
+    let response = request.send().await.map_err(request_failed)?;
+
+    let status = response.status();
+    let status_error = FetchError::Status {
+        url: url.to_string(),
+        status,
+    };
+    if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file may already hold the complete download
+        if content_range_total(&response) == Some(resume_from) {
+            return Ok(());
+        }
+        // Restart the download from scratch
+        tokio::fs::remove_file(dest).await.map_err(write_failed)?;
+        return Err(resume_rejected());
+    }
+    if status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+    {
+        return Err(AttemptError::Transient(status_error));
+    }
+    if !status.is_success() {
+        return Err(AttemptError::Fatal(status_error));
+    }
+
+    let resuming = resume_from > 0 && status == StatusCode::PARTIAL_CONTENT;
+    if resuming && content_range_start(&response) != Some(resume_from) {
+        // Appending a range from another offset would corrupt the zip, so start over without one
+        tokio::fs::remove_file(dest).await.map_err(write_failed)?;
+        return Err(resume_rejected());
+    }
+
+    let mut options = OpenOptions::new();
+    if resuming {
+        report(Event::DownloadResumed {
+            offset: resume_from,
+        });
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+    let mut file = options
+        .create(true)
+        .open(dest)
+        .await
+        .map_err(write_failed)?;
+
+    let mut downloaded = if resuming { resume_from } else { 0 };
+    let total_size = response.content_length().map(|len| len + downloaded);
+
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(request_failed)?;
+        file.write_all(&chunk).await.map_err(write_failed)?;
+        downloaded += chunk.len() as u64;
+
+        if let Some(total) = total_size.filter(|size| *size > 0) {
+            report(Event::DownloadProgress { downloaded, total });
+        }
+    }
+    file.flush().await.map_err(write_failed)?;
+
+    if let Some(total_size) = total_size
+        && downloaded < total_size
+    {
+        return Err(AttemptError::Transient(FetchError::Incomplete {
+            url: url.to_string(),
+            downloaded,
+            expected: total_size,
+        }));
+    }
+
+    Ok(())
+}
+
+/// Parses the start offset from a `Content-Range: bytes <start>-<end>/<total>` header.
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.strip_prefix("bytes ")?;
+    let (start, _) = range.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// Parses the total size from a `Content-Range: bytes */<total>` header.
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (_, total) = value.rsplit_once('/')?;
+    total.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::test_support::{TestRequest, TestResponse, serve};
+
+    const BODY: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    /// Self-signed root certificate standing in for a private CA.
+    const TEST_CA_PEM: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBjzCCATWgAwIBAgIUHxI6tajYVhjnm5LNCwd7lKVILzQwCgYIKoZIzj0EAwIw
+HDEaMBgGA1UEAwwRYmVkcm9ja2NpIHRlc3QgQ0EwIBcNMjYxMDE4MTQzNzU1WhgP
+MjEyNjA5MjQxNDM3NTVaMBwxGjAYBgNVBAMMEWJlZHJvY2tjaSB0ZXN0IENBMFkw
+EwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAENX/IQq8byFzn3dRK7wP2VixZkUDFAnwf
+q0N2mLcHniKO682tSlT9XtZ4P2cX0rZkrDWomXOMvGZuHAXzb/I6L6NTMFEwHQYD
+VR0OBBYEFJfvYXNrH7pfvYRdkmA14JraMcfJMB8GA1UdIwQYMBaAFJfvYXNrH7pf
+vYRdkmA14JraMcfJMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIg
+Hll5+MgWrWESzMLZpBTO/Cjm2JcKnimB/C+SIc92vuMCIQDdbd4RbdK5IwPSHy81
+wmpNvbxzBVTSQijjrDeMc/gBKg==
+-----END CERTIFICATE-----
+";
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        }
+    }
+
+    /// Serves `BODY`, honouring `Range: bytes=<start>-` requests.
+    fn ranged(request: &TestRequest) -> TestResponse {
+        let start = request
+            .header("Range")
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+        match start {
+            Some(start) => TestResponse::new("206 Partial Content", &BODY[start..]).header(
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, BODY.len() - 1, BODY.len()),
+            ),
+            None => TestResponse::new("200 OK", BODY),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_streams_to_file() {
+        let server = serve(ranged).await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+
+        fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_resumes_dropped_transfer() {
+        let server = serve(|request| {
+            if request.index == 0 {
+                TestResponse::new("200 OK", BODY).truncate_at(10)
+            } else {
+                assert_eq!(request.header("Range"), Some("bytes=10-"));
+                ranged(request)
+            }
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+
+        fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_resumes_existing_partial_file() {
+        let server = serve(ranged).await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+        std::fs::write(&dest, &BODY[..20]).unwrap();
+
+        fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_restarts_when_range_ignored() {
+        let server = serve(|_| TestResponse::new("200 OK", BODY)).await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+        std::fs::write(&dest, b"stale partial content").unwrap();
+
+        fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_restarts_when_resumed_from_other_offset() {
+        let server = serve(|request| match request.header("Range") {
+            Some(_) => TestResponse::new("206 Partial Content", &BODY[5..]).header(
+                "Content-Range",
+                format!("bytes 5-{}/{}", BODY.len() - 1, BODY.len()),
+            ),
+            None => TestResponse::new("200 OK", BODY),
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+        std::fs::write(&dest, &BODY[..20]).unwrap();
+
+        fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+        assert_eq!(server.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retries_server_errors() {
+        let server = serve(|request| {
+            if request.index < 2 {
+                TestResponse::new("503 Service Unavailable", "")
+            } else {
+                TestResponse::new("200 OK", BODY)
+            }
+        })
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+
+        fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+        assert_eq!(server.request_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_gives_up_after_max_attempts() {
+        let server = serve(|_| TestResponse::new("503 Service Unavailable", "")).await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+
+        let result = fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(FetchError::RetriesExhausted { attempts: 3, .. })
+        ));
+        assert_eq!(server.request_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_not_found_is_not_retried() {
+        let server = serve(|_| TestResponse::new("404 Not Found", "")).await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip.part");
+
+        let result = fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(FetchError::Status {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_write_failure_is_not_retried() {
+        let server = serve(ranged).await;
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("missing/server.zip.part");
+
+        let result = fetch_to_file(
+            &http_client(&NetworkConfig::default()).unwrap(),
+            &server.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(FetchError::Write { path, .. }) if path == dest));
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_through_proxy() {
+        // A forward proxy receives the absolute URL of the origin, which need not resolve
+        let proxy = serve(|request| {
+            let authorized =
+                request.header("Proxy-Authorization") == Some("Basic dXNlcjpzZWNyZXQ=");
+            if request.target == "http://bedrock.invalid/server.zip" && authorized {
+                TestResponse::new("200 OK", "zip")
+            } else {
+                TestResponse::new("407 Proxy Authentication Required", "")
+            }
+        })
+        .await;
+        let config = NetworkConfig {
+            proxy: Some(proxy.base_url.replace("http://", "http://user:secret@")),
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip");
+
+        fetch_to_file(
+            &http_client(&config).unwrap(),
+            "http://bedrock.invalid/server.zip",
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), b"zip");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_bypasses_proxy_for_no_proxy_hosts() {
+        let proxy = serve(|_| TestResponse::new("502 Bad Gateway", "")).await;
+        let origin = serve(|_| TestResponse::new("200 OK", "zip")).await;
+        let config = NetworkConfig {
+            proxy: Some(proxy.base_url.clone()),
+            no_proxy: Some("127.0.0.1".to_string()),
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("server.zip");
+
+        fetch_to_file(
+            &http_client(&config).unwrap(),
+            &origin.url("/server.zip"),
+            &dest,
+            &fast_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(proxy.request_count(), 0);
+        assert_eq!(origin.request_count(), 1);
+    }
+
+    #[test]
+    fn test_http_client_loads_ca_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("ca.pem");
+        let config = NetworkConfig {
+            ca_bundle: Some(bundle.clone()),
+            ..Default::default()
+        };
+
+        std::fs::write(&bundle, TEST_CA_PEM).unwrap();
+        assert!(http_client(&config).is_ok());
+
+        std::fs::write(&bundle, "not a certificate").unwrap();
+        assert!(http_client(&config).is_err());
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+    }
+}
@@ -1 +1,10 @@
+mod extract;
+mod fetch;
+pub mod network;
+pub mod offline;
 pub mod server;
+#[cfg(test)]
+mod test_support;
+pub mod version_source;
+
+pub use fetch::FetchError;
@@ -0,0 +1,58 @@
+use crate::download::server::ServerDownloadError;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Environment variable enabling offline mode, e.g. `BEDROCK_OFFLINE=1`.
+pub const OFFLINE_ENV: &str = "BEDROCK_OFFLINE";
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables offline mode for the whole process.
+///
+/// In offline mode, downloads and latest-version lookups fail immediately with
+/// [`ServerDownloadError::Offline`] instead of touching the network. Installing from a local
+/// zip and anything that only uses installed servers keep working.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Returns true if offline mode was enabled by [`set_offline`] or the `BEDROCK_OFFLINE`
+/// environment variable.
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+        || std::env::var(OFFLINE_ENV).is_ok_and(|value| is_truthy(&value))
+}
+
+/// Fails with [`ServerDownloadError::Offline`] if offline mode is enabled.
+///
+/// # Arguments
+///
+/// * `action` - What needed the network, for the error message
+pub(crate) fn ensure_online(action: &str) -> Result<(), ServerDownloadError> {
+    if is_offline() {
+        return Err(ServerDownloadError::Offline(action.to_string()));
+    }
+
+    Ok(())
+}
+
+fn is_truthy(value: &str) -> bool {
+    !matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "" | "0" | "f" | "false" | "n" | "no" | "off"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_truthy() {
+        for value in ["1", "true", "yes", "on", "TRUE"] {
+            assert!(is_truthy(value), "{}", value);
+        }
+        for value in ["", "0", "f", "false", "n", "no", "off", "False"] {
+            assert!(!is_truthy(value), "{}", value);
+        }
+    }
+}
@@ -1,30 +1,129 @@
-use futures::StreamExt;
-use headless_chrome::{Browser, LaunchOptions};
-use regex::Regex;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
-use zip::ZipArchive;
+use crate::dedup::{STORE_DIR_NAME, link_into_store};
+use crate::download::extract::{DETECT_COPY_INFIX, create_staging_dir, extract_archive};
+use crate::download::fetch::{FetchError, RetryPolicy, fetch_to_file, http_client};
+use crate::download::network::network_config;
+use crate::download::offline::ensure_online;
+#[cfg(feature = "browser-lookup")]
+use crate::download::version_source::{BrowserVersionSource, FallbackVersionSource};
+use crate::download::version_source::{
+    HttpVersionSource, VersionSource, linux_artifact_dir, version_from_url,
+};
+use crate::error::ErrorKind;
+use crate::error::display_chain;
+use crate::fs_util::copy_dir;
+use crate::integrity::{InstallManifest, IntegrityError, hash_file};
+use crate::report::{Event, report};
+use crate::server::{
+    Channel, ServerError, compare_versions, detect_server_version, install_staged, latest_installed,
+};
+use crate::server_path::ServerPathError;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ServerDownloadError {
     #[error("EULA and Privacy Policy not accepted")]
     EulaAndPrivacyPolicyNotAccepted,
-    #[error("Failed to download server: {0}")]
-    DownloadFailed(String),
-    #[error("Failed to read server zip: {0}")]
-    ZipReadFailed(String),
-    #[error("Failed to create temporary file: {0}")]
-    TempFileCreationFailed(String),
-    #[error("Failed to extract server files: {0}")]
-    ExtractionFailed(String),
-    #[error("Invalid download path: {0}")]
-    InvalidPath(String),
+    #[error("Failed to download server")]
+    DownloadFailed(#[source] FetchError),
+    #[error("Invalid network configuration")]
+    NetworkConfigInvalid(#[source] FetchError),
+    #[error("Failed to read server zip {}", path.display())]
+    ZipReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid server zip {}", path.display())]
+    ZipInvalid {
+        path: PathBuf,
+        #[source]
+        source: zip::result::ZipError,
+    },
+    #[error("Refusing to extract zip entry with unsafe path: {0}")]
+    UnsafeZipEntry(String),
+    #[error("Failed to create temporary files in {}", path.display())]
+    TempFileCreationFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to extract server file {}", path.display())]
+    ExtractionFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid download path {}", path.display())]
+    InvalidPath {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
     #[error("Server version {0} already installed")]
     ServerAlreadyInstalled(String),
+    #[error("Failed to look up latest version: {0}")]
+    VersionLookupFailed(String),
+    #[error("Failed to query {url}")]
+    LookupRequestFailed {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("Invalid response from {url}")]
+    LookupResponseInvalid {
+        url: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[cfg(feature = "browser-lookup")]
+    #[error("Failed to {action} in the headless browser")]
+    BrowserFailed {
+        action: &'static str,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Server version {0} does not exist (download returned 404 Not Found)")]
+    VersionNotFound(String),
+    #[error("Checksum mismatch: expected SHA-256 {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+    #[error("Failed to detect server version. Pass the version explicitly instead.")]
+    VersionDetectionFailed(#[source] ServerError),
+    #[error("Cannot {0} in offline mode")]
+    Offline(String),
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Server reports version {actual}, expected {expected}")]
+    VersionMismatch { expected: String, actual: String },
+    #[error(transparent)]
+    ServerPath(#[from] ServerPathError),
+    #[error(transparent)]
+    Server(#[from] ServerError),
 }
 
-const EULA_NOT_ACCEPTED_TEXT: &str = r#"
+impl ServerDownloadError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ServerDownloadError::EulaAndPrivacyPolicyNotAccepted
+            | ServerDownloadError::InvalidPath { .. }
+            | ServerDownloadError::ServerAlreadyInstalled(_)
+            | ServerDownloadError::VersionNotFound(_)
+            | ServerDownloadError::Offline(_) => ErrorKind::Usage,
+            ServerDownloadError::Cancelled => ErrorKind::Cancelled,
+            ServerDownloadError::ServerPath(e) => e.kind(),
+            ServerDownloadError::Server(e) => e.kind(),
+            ServerDownloadError::Integrity(e) => e.kind(),
+            _ => ErrorKind::Infrastructure,
+        }
+    }
+}
+
+pub const EULA_NOT_ACCEPTED_TEXT: &str = r#"
 By proceeding, you agree to the Minecraft End User License Agreement:
 https://minecraft.net/eula
 and the Privacy Policy:
@@ -33,14 +132,54 @@ https://go.microsoft.com/fwlink/?LinkId=521839
 If you do not agree, you must not use this software.
 "#;
 
-/// Downloads the Bedrock Dedicated Server from the Minecraft website.
+/// Default location of the Linux server zips, as `<base>/<bin-linux|bin-linux-preview>/bedrock-server-<version>.zip`.
+pub const DEFAULT_DOWNLOAD_BASE_URL: &str = "https://www.minecraft.net/bedrockdedicatedserver";
+
+/// Environment variable overriding the download base URL, e.g. to use an internal mirror.
+/// Zips are then downloaded from `<base>/bedrock-server-<version>.zip` for any channel.
+pub const DOWNLOAD_BASE_URL_ENV: &str = "BEDROCK_DOWNLOAD_BASE_URL";
+
+/// How long to wait for a server to report its version when it can't be inferred otherwise.
+const VERSION_DETECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options controlling how [`download_server`] and [`install_server_from_file`] install a server.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Whether to force reinstallation if the server is already installed.
+    pub force_reinstall: bool,
+    /// Expected SHA-256 of the server zip, as hex. The install fails if it doesn't match.
+    pub expected_sha256: Option<String>,
+    /// Base URL to download server zips from, as `<base>/bedrock-server-<version>.zip`. Falls
+    /// back to the `BEDROCK_DOWNLOAD_BASE_URL` environment variable, then the official site.
+    pub base_url: Option<String>,
+    /// Whether to install a release or a preview build. Preview builds are installed as
+    /// `<version>-preview`.
+    pub channel: Channel,
+    /// Launch the extracted server briefly and fail if the version it reports doesn't match the
+    /// requested one.
+    pub verify_version: bool,
+    /// Aborts the download or install when cancelled, returning
+    /// [`ServerDownloadError::Cancelled`]. Extracted files are removed, while a partially
+    /// downloaded zip is kept so the next download can resume it.
+    pub cancel: CancellationToken,
+}
+
+/// Downloads the Bedrock Dedicated Server from the Minecraft website or a configured mirror.
+///
+/// The zip is streamed to a partial file in `download_path` and transient network failures are
+/// retried with exponential backoff. If the download still fails, the partial file is kept and
+/// resumed by the next call.
+///
+/// After extraction, an [`InstallManifest`] recording every installed file is written into the
+/// version directory so the install can later be checked with
+/// [`verify_install`](crate::integrity::verify_install).
 ///
 /// # Arguments
 ///
 /// * `version` - The version of the Bedrock Dedicated Server to download.
-/// * `download_path` - The base path to download the server to. The server will be installed in a subdirectory named after the version.
+/// * `download_path` - The base path to download the server to. The server will be installed in a subdirectory named after the version (and channel).
 /// * `accepted_eula_and_privacy_policy` - Whether the EULA and Privacy Policy have been accepted. Must be true to download the server.
-/// * `force_reinstall` - Whether to force reinstallation if the server is already installed.
+/// * `options` - Reinstall, checksum, mirror and channel options, see [`DownloadOptions`].
 ///
 /// # Returns
 ///
@@ -50,234 +189,805 @@ pub async fn download_server(
     version: &str,
     download_path: PathBuf,
     accepted_eula_and_privacy_policy: bool,
-    force_reinstall: bool,
+    options: &DownloadOptions,
 ) -> Result<(), ServerDownloadError> {
-    if !accepted_eula_and_privacy_policy {
-        println!("{}", EULA_NOT_ACCEPTED_TEXT);
-        return Err(ServerDownloadError::EulaAndPrivacyPolicyNotAccepted);
+    check_eula(accepted_eula_and_privacy_policy)?;
+    prepare_download_path(&download_path)?;
+    check_not_installed(&download_path, version, options)?;
+    ensure_online(&format!(
+        "download server version {}",
+        options.channel.dir_name(version)
+    ))?;
+
+    let download_url = get_download_url(options, version);
+    report(Event::DownloadStarted {
+        version: options.channel.dir_name(version),
+        url: download_url.clone(),
+    });
+
+    // Stream to a partial file next to the install so an interrupted download can be resumed
+    let partial_path = download_path.join(format!(
+        ".bedrock-server-{}.zip.part",
+        options.channel.dir_name(version)
+    ));
+    let client =
+        http_client(&network_config()).map_err(ServerDownloadError::NetworkConfigInvalid)?;
+    until_cancelled(&options.cancel, async {
+        fetch_to_file(
+            &client,
+            &download_url,
+            &partial_path,
+            &RetryPolicy::default(),
+        )
+        .await
+        .map_err(|e| match e {
+            FetchError::Status {
+                status: reqwest::StatusCode::NOT_FOUND,
+                ..
+            } => ServerDownloadError::VersionNotFound(version.to_string()),
+            e => ServerDownloadError::DownloadFailed(e),
+        })
+    })
+    .await?;
+    report(Event::DownloadComplete);
+
+    let result = until_cancelled(
+        &options.cancel,
+        install_zip(
+            &partial_path,
+            Some(version),
+            &download_path,
+            &download_url,
+            options,
+        ),
+    )
+    .await;
+    match &result {
+        // The install is complete, the zip is no longer needed
+        Ok(_) => {
+            std::fs::remove_file(&partial_path).ok();
+        }
+        // A complete but corrupt download can't be resumed, start from scratch next time
+        Err(ServerDownloadError::ChecksumMismatch { .. })
+        | Err(ServerDownloadError::ZipInvalid { .. })
+        | Err(ServerDownloadError::UnsafeZipEntry(_))
+        | Err(ServerDownloadError::VersionMismatch { .. }) => {
+            std::fs::remove_file(&partial_path).ok();
+        }
+        Err(_) => {}
     }
 
-    // Create version-specific directory
-    let version_path = download_path.join(version);
+    result.map(|_| ())
+}
 
-    if !force_reinstall {
-        if version_path.exists() {
-            return Err(ServerDownloadError::ServerAlreadyInstalled(
-                version.to_string(),
-            ));
-        }
+/// Installs the Bedrock Dedicated Server from a local zip, e.g. one pre-fetched for an
+/// air-gapped machine.
+///
+/// If `version` is not given, it is inferred from a `bedrock-server-<version>.zip` file name,
+/// or failing that by briefly launching the extracted server and reading the version it reports.
+///
+/// # Arguments
+///
+/// * `zip_path` - Path to the server zip.
+/// * `version` - The version the zip contains, if known.
+/// * `download_path` - The base path to install the server to. The server will be installed in a subdirectory named after the version (and channel).
+/// * `accepted_eula_and_privacy_policy` - Whether the EULA and Privacy Policy have been accepted. Must be true to install the server.
+/// * `options` - Reinstall, checksum and channel options, see [`DownloadOptions`]. `base_url` is ignored.
+///
+/// # Returns
+///
+/// * `Ok(String)` - The installed version.
+/// * `Err(ServerDownloadError)` - If the server was not installed successfully.
+pub async fn install_server_from_file(
+    zip_path: &Path,
+    version: Option<&str>,
+    download_path: PathBuf,
+    accepted_eula_and_privacy_policy: bool,
+    options: &DownloadOptions,
+) -> Result<String, ServerDownloadError> {
+    check_eula(accepted_eula_and_privacy_policy)?;
+    prepare_download_path(&download_path)?;
+
+    if !zip_path.is_file() {
+        return Err(ServerDownloadError::ZipReadFailed {
+            path: zip_path.to_path_buf(),
+            source: std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "does not exist or is not a file",
+            ),
+        });
+    }
+
+    let version = version.map(str::to_string).or_else(|| {
+        zip_path
+            .file_name()
+            .and_then(|name| version_from_url(&name.to_string_lossy()))
+    });
+    if let Some(version) = &version {
+        check_not_installed(&download_path, version, options)?;
+    }
+
+    let zip_path =
+        std::fs::canonicalize(zip_path).map_err(|source| ServerDownloadError::ZipReadFailed {
+            path: zip_path.to_path_buf(),
+            source,
+        })?;
+    let source_url = format!("file://{}", zip_path.display());
+
+    report(Event::InstallStarted {
+        zip_path: zip_path.clone(),
+    });
+    until_cancelled(
+        &options.cancel,
+        install_zip(
+            &zip_path,
+            version.as_deref(),
+            &download_path,
+            &source_url,
+            options,
+        ),
+    )
+    .await
+}
+
+/// Runs `future` unless `cancel` is cancelled first. Dropping the future cleans up after it, as
+/// staging directories and launched servers are removed and killed on drop.
+async fn until_cancelled<T>(
+    cancel: &CancellationToken,
+    future: impl Future<Output = Result<T, ServerDownloadError>>,
+) -> Result<T, ServerDownloadError> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(ServerDownloadError::Cancelled),
+        result = future => result,
+    }
+}
+
+fn check_eula(accepted_eula_and_privacy_policy: bool) -> Result<(), ServerDownloadError> {
+    if !accepted_eula_and_privacy_policy {
+        return Err(ServerDownloadError::EulaAndPrivacyPolicyNotAccepted);
     }
 
-    // Validate download path
+    Ok(())
+}
+
+fn prepare_download_path(download_path: &Path) -> Result<(), ServerDownloadError> {
     if !download_path.exists() {
-        std::fs::create_dir_all(&download_path).map_err(|e| {
-            ServerDownloadError::InvalidPath(format!("Failed to create directory: {}", e))
+        std::fs::create_dir_all(download_path).map_err(|source| {
+            ServerDownloadError::InvalidPath {
+                path: download_path.to_path_buf(),
+                source,
+            }
         })?;
     }
     if !download_path.is_dir() {
-        return Err(ServerDownloadError::InvalidPath(
-            "Path must be a directory".to_string(),
-        ));
+        return Err(ServerDownloadError::InvalidPath {
+            path: download_path.to_path_buf(),
+            source: std::io::ErrorKind::NotADirectory.into(),
+        });
     }
 
-    println!("Downloading Bedrock Server version {}...", version);
-    let download_url = get_download_url(version);
+    Ok(())
+}
 
-    // Download with progress feedback
-    let response = reqwest::get(&download_url).await.map_err(|e| {
-        ServerDownloadError::DownloadFailed(format!("Failed to connect to server: {}", e))
-    })?;
+fn check_not_installed(
+    download_path: &Path,
+    version: &str,
+    options: &DownloadOptions,
+) -> Result<(), ServerDownloadError> {
+    let dir_name = options.channel.dir_name(version);
+    if !options.force_reinstall && download_path.join(&dir_name).exists() {
+        return Err(ServerDownloadError::ServerAlreadyInstalled(dir_name));
+    }
 
-    let total_size = response.content_length().unwrap_or(0);
-    let mut downloaded = 0;
-    let mut content = Vec::new();
+    Ok(())
+}
 
-    let mut stream = response.bytes_stream();
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| {
-            ServerDownloadError::DownloadFailed(format!("Failed to read chunk: {}", e))
-        })?;
-        content.extend_from_slice(&chunk);
-        downloaded += chunk.len() as u64;
+/// Verifies, extracts and installs a server zip, returning the installed version.
+///
+/// The zip is extracted next to the install and moved into place only once complete, so an
+/// interrupted extraction never leaves a half-populated version directory behind.
+async fn install_zip(
+    zip_path: &Path,
+    version: Option<&str>,
+    download_path: &Path,
+    source_url: &str,
+    options: &DownloadOptions,
+) -> Result<String, ServerDownloadError> {
+    let zip_sha256 = hash_file(zip_path)?;
+    if let Some(expected) = &options.expected_sha256
+        && !expected.eq_ignore_ascii_case(&zip_sha256)
+    {
+        return Err(ServerDownloadError::ChecksumMismatch {
+            expected: expected.to_lowercase(),
+            actual: zip_sha256,
+        });
+    }
 
-        if total_size > 0 {
-            let percentage = (downloaded as f64 / total_size as f64 * 100.0) as u32;
-            print!("\rDownloading: {}%", percentage);
-            std::io::stdout().flush().ok();
+    report(Event::ExtractionStarted);
+    let staging = create_staging_dir(
+        download_path,
+        &options.channel.dir_name(version.unwrap_or("unknown")),
+    )?;
+    extract_archive(zip_path, staging.path())?;
+    report(Event::ExtractionComplete);
+
+    let (version, server_version) = match version {
+        Some(version) if options.verify_version => {
+            report(Event::Status("Verifying server version...".to_string()));
+            let reported = detect_staged_version(staging.path()).await?;
+            if reported != version {
+                return Err(ServerDownloadError::VersionMismatch {
+                    expected: version.to_string(),
+                    actual: reported,
+                });
+            }
+            report(Event::VersionDetected(reported.clone()));
+            (version.to_string(), Some(reported))
         }
+        Some(version) => (version.to_string(), None),
+        None => {
+            report(Event::Status("Detecting server version...".to_string()));
+            let version = detect_staged_version(staging.path()).await?;
+            report(Event::VersionDetected(version.clone()));
+            check_not_installed(download_path, &version, options)?;
+            (version.clone(), Some(version))
+        }
+    };
+
+    report(Event::Status("Writing integrity manifest...".to_string()));
+    let mut manifest =
+        InstallManifest::generate(staging.path(), &version, source_url, &zip_sha256)?;
+    manifest.server_version = server_version;
+    manifest.write(staging.path())?;
+
+    // Share files identical to those of other installed versions. This only saves space, so a
+    // failure leaves a complete, if larger, install.
+    match link_into_store(
+        staging.path(),
+        &manifest.files,
+        &download_path.join(STORE_DIR_NAME),
+    ) {
+        Ok(stats) if stats.linked_files > 0 => report(Event::FilesShared {
+            files: stats.linked_files,
+            bytes: stats.bytes_saved,
+        }),
+        Ok(_) => {}
+        Err(e) => report(Event::Warning(format!(
+            "Failed to deduplicate server files: {}",
+            display_chain(&e)
+        ))),
     }
-    println!("\nDownload complete!");
 
-    println!("Extracting server files...");
-    // Create a temporary file for the zip
-    let temp_zip = tempfile::NamedTempFile::new()
-        .map_err(|e| ServerDownloadError::TempFileCreationFailed(e.to_string()))?;
+    // Extraction can't be interrupted, so check once more before the install becomes visible
+    if options.cancel.is_cancelled() {
+        return Err(ServerDownloadError::Cancelled);
+    }
 
-    temp_zip.as_file().write_all(&content).map_err(|e| {
-        ServerDownloadError::ZipReadFailed(format!("Failed to write zip file: {}", e))
-    })?;
+    // Moved into place while still locked, after which dropping it has nothing left to remove
+    install_staged(
+        staging.path(),
+        &download_path.join(options.channel.dir_name(&version)),
+    )?;
 
-    // Extract the zip file
-    let mut archive = ZipArchive::new(temp_zip.as_file()).map_err(|e| {
-        ServerDownloadError::ExtractionFailed(format!("Failed to open zip archive: {}", e))
-    })?;
+    Ok(version)
+}
 
-    let total_files = archive.len();
-    for i in 0..total_files {
-        if let Ok(mut file) = archive.by_index(i) {
-            let outpath = version_path.join(file.name());
-            if file.name().ends_with('/') {
-                std::fs::create_dir_all(&outpath).map_err(|e| {
-                    ServerDownloadError::ExtractionFailed(format!(
-                        "Failed to create directory: {}",
-                        e
-                    ))
-                })?;
-            } else {
-                if let Some(parent) = outpath.parent() {
-                    std::fs::create_dir_all(parent).map_err(|e| {
-                        ServerDownloadError::ExtractionFailed(format!(
-                            "Failed to create parent directory: {}",
-                            e
-                        ))
-                    })?;
-                }
-                let mut outfile = File::create(&outpath).map_err(|e| {
-                    ServerDownloadError::ExtractionFailed(format!("Failed to create file: {}", e))
-                })?;
-                std::io::copy(&mut file, &mut outfile).map_err(|e| {
-                    ServerDownloadError::ExtractionFailed(format!("Failed to extract file: {}", e))
-                })?;
-            }
-            print!("\rExtracting: {}/{} files", i + 1, total_files);
-            std::io::stdout().flush().ok();
-        }
-    }
-    println!("\nExtraction complete!");
+/// Launches a throwaway copy of a freshly extracted server to read its version, so nothing the
+/// server writes on startup ends up in the install or its manifest.
+async fn detect_staged_version(staging_path: &Path) -> Result<String, ServerDownloadError> {
+    // Named after the staging directory, so a leftover copy is cleaned up along with it
+    let mut prefix = staging_path.file_name().unwrap_or_default().to_os_string();
+    prefix.push(DETECT_COPY_INFIX);
+    let parent = staging_path.parent().unwrap_or(staging_path);
+    let copy_failed = |source| ServerDownloadError::TempFileCreationFailed {
+        path: parent.to_path_buf(),
+        source,
+    };
+    let scratch = tempfile::Builder::new()
+        .prefix(&prefix)
+        .tempdir_in(parent)
+        .map_err(copy_failed)?;
+    copy_dir(staging_path, scratch.path(), &|_, source| {
+        copy_failed(source)
+    })?;
 
-    Ok(())
+    detect_server_version(scratch.path(), VERSION_DETECTION_TIMEOUT)
+        .await
+        .map_err(ServerDownloadError::VersionDetectionFailed)
 }
 
-fn get_download_url(version: &str) -> String {
+fn get_download_url(options: &DownloadOptions, version: &str) -> String {
+    let base_url = options
+        .base_url
+        .clone()
+        .or_else(|| std::env::var(DOWNLOAD_BASE_URL_ENV).ok())
+        .unwrap_or_else(|| {
+            format!(
+                "{}/{}",
+                DEFAULT_DOWNLOAD_BASE_URL,
+                linux_artifact_dir(options.channel)
+            )
+        });
+
     format!(
-        "https://www.minecraft.net/bedrockdedicatedserver/bin-linux/bedrock-server-{}.zip",
+        "{}/bedrock-server-{}.zip",
+        base_url.trim_end_matches('/'),
         version
     )
 }
 
-/// Gets the latest version of the Bedrock Dedicated Server by parsing the Minecraft download page.
+/// Gets the latest version of the Bedrock Dedicated Server in the given channel.
+///
+/// Queries the JSON download links endpoint first (see [`HttpVersionSource`]). With the
+/// `browser-lookup` feature enabled, falls back to scraping the download page with headless
+/// Chrome if that fails.
+///
+/// # Arguments
+///
+/// * `channel` - Whether to look up the latest release or preview build
 ///
 /// # Returns
 ///
 /// * `Ok(String)` - The latest version string if successful
 /// * `Err(ServerDownloadError)` - If the version could not be retrieved
-pub async fn get_latest_version() -> Result<String, ServerDownloadError> {
-    println!("Launching headless browser...");
-
-    // Launch headless Chrome with options to handle HTTP/2 issues
-    let launch_options = LaunchOptions {
-        headless: true,
-        args: vec![
-            std::ffi::OsStr::new("--disable-http2"),
-            std::ffi::OsStr::new(
-                "--user-agent=Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-            ),
-            std::ffi::OsStr::new("--disable-blink-features=AutomationControlled"),
-            std::ffi::OsStr::new("--no-sandbox"),
-            std::ffi::OsStr::new("--disable-dev-shm-usage"),
-        ],
-        ..Default::default()
-    };
+pub async fn get_latest_version(channel: Channel) -> Result<String, ServerDownloadError> {
+    get_latest_version_cancellable(channel, &CancellationToken::new()).await
+}
 
-    let browser = Browser::new(launch_options).map_err(|e| {
-        ServerDownloadError::DownloadFailed(format!("Failed to launch browser: {}", e))
-    })?;
+/// Like [`get_latest_version`], but returns [`ServerDownloadError::Cancelled`] as soon as
+/// `cancel` is cancelled. Any headless browser launched for the lookup is closed.
+pub async fn get_latest_version_cancellable(
+    channel: Channel,
+    cancel: &CancellationToken,
+) -> Result<String, ServerDownloadError> {
+    #[cfg(feature = "browser-lookup")]
+    let source = FallbackVersionSource::new(HttpVersionSource::default(), BrowserVersionSource);
+    #[cfg(not(feature = "browser-lookup"))]
+    let source = HttpVersionSource::default();
+
+    until_cancelled(cancel, source.latest_version(channel)).await
+}
 
-    // Navigate to the download page
-    let tab = browser.new_tab().map_err(|e| {
-        ServerDownloadError::DownloadFailed(format!("Failed to create new tab: {}", e))
-    })?;
+/// The newest installed server in a channel compared against the latest available one.
+#[derive(Debug, Clone)]
+pub struct UpdateCheck {
+    pub channel: Channel,
+    /// The newest installed version, without any channel suffix, if one is installed.
+    pub installed: Option<String>,
+    /// The latest version available for download.
+    pub latest: String,
+}
 
-    tab.navigate_to("https://minecraft.net/en-us/download/server/bedrock/")
-        .map_err(|e| {
-            ServerDownloadError::DownloadFailed(format!("Failed to navigate to page: {}", e))
-        })?;
+impl UpdateCheck {
+    /// Returns true if nothing is installed or the latest available version is newer.
+    pub fn is_outdated(&self) -> bool {
+        self.installed
+            .as_deref()
+            .is_none_or(|installed| compare_versions(installed, &self.latest) == Ordering::Less)
+    }
+}
 
-    // Wait for the page to load and JavaScript to execute
-    println!("Waiting for page to load...");
-    std::thread::sleep(std::time::Duration::from_secs(5));
-
-    // Try to find the download button/link for Linux server
-    let result = tab
-        .evaluate(
-            r#"
-            // Find all links with href containing bedrock-server
-            const links = Array.from(document.querySelectorAll('a[href*="bedrock-server"]'));
-            const linuxLink = links.find(link => link.href.includes('bin-linux'));
-            if (linuxLink) {
-                linuxLink.href;
-            } else {
-                // Try to find by aria-label
-                const button = document.querySelector('a[aria-label="serverBedrockLinux"]');
-                button ? button.href : null;
-            }
-            "#,
-            false,
+/// Compares the newest installed server in `channel` against the latest available version.
+///
+/// # Arguments
+///
+/// * `channel` - Whether to check releases or preview builds
+///
+/// # Returns
+///
+/// * `Ok(UpdateCheck)` - The installed and latest versions
+/// * `Err(ServerDownloadError)` - If the installed servers could not be listed or the latest
+///   version could not be retrieved
+pub async fn check_for_update(channel: Channel) -> Result<UpdateCheck, ServerDownloadError> {
+    let installed = match latest_installed(channel) {
+        Ok(name) => name,
+        // No server directory yet just means nothing is installed
+        Err(ServerError::ServerPath(ServerPathError::Missing(_))) => None,
+        Err(e) => return Err(e.into()),
+    }
+    .map(|name| Channel::from_dir_name(&name).1.to_string());
+    let latest = get_latest_version(channel).await?;
+
+    Ok(UpdateCheck {
+        channel,
+        installed,
+        latest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::test_support::{TestResponse, serve, write_zip};
+    use crate::integrity::verify_install;
+    use crate::test_support::FakeServer;
+
+    /// Writes a zip of a server that changes its directory on startup, then reports its version.
+    fn server_zip(dir: &Path, name: &str) -> PathBuf {
+        let script = FakeServer::new()
+            .run("mkdir -p worlds")
+            .run("echo 'level-name=changed' >> server.properties")
+            .log("INFO", "Version: 1.21.84.1")
+            .run("sleep 30")
+            .script();
+        let zip_path = dir.join(name);
+        write_zip(
+            &zip_path,
+            &[
+                ("bedrock_server", script.as_bytes(), 0o755),
+                ("server.properties", b"server-name=test\n", 0o644),
+            ],
+        );
+        zip_path
+    }
+
+    #[test]
+    fn test_download_url_uses_base_url() {
+        let options = DownloadOptions {
+            base_url: Some("https://mirror.example.com/bedrock/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_download_url(&options, "1.21.84.1"),
+            "https://mirror.example.com/bedrock/bedrock-server-1.21.84.1.zip"
+        );
+    }
+
+    #[test]
+    fn test_update_check_is_outdated() {
+        let check = |installed: Option<&str>, latest: &str| UpdateCheck {
+            channel: Channel::Release,
+            installed: installed.map(str::to_string),
+            latest: latest.to_string(),
+        };
+
+        assert!(check(None, "1.21.84.1").is_outdated());
+        assert!(check(Some("1.21.9.1"), "1.21.84.1").is_outdated());
+        assert!(!check(Some("1.21.84.1"), "1.21.84.1").is_outdated());
+        assert!(!check(Some("1.21.100.1"), "1.21.84.1").is_outdated());
+    }
+
+    #[test]
+    fn test_download_url_per_channel() {
+        let release = DownloadOptions::default();
+        let preview = DownloadOptions {
+            channel: Channel::Preview,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_download_url(&release, "1.21.84.1"),
+            "https://www.minecraft.net/bedrockdedicatedserver/bin-linux/bedrock-server-1.21.84.1.zip"
+        );
+        assert_eq!(
+            get_download_url(&preview, "1.21.100.20"),
+            "https://www.minecraft.net/bedrockdedicatedserver/bin-linux-preview/bedrock-server-1.21.100.20.zip"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preview_installed_alongside_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = server_zip(dir.path(), "bedrock-server-1.21.84.1.zip");
+        let install_path = dir.path().join("server");
+        let preview = DownloadOptions {
+            channel: Channel::Preview,
+            ..Default::default()
+        };
+
+        install_server_from_file(
+            &zip_path,
+            None,
+            install_path.clone(),
+            true,
+            &DownloadOptions::default(),
         )
-        .map_err(|e| {
-            ServerDownloadError::DownloadFailed(format!("Failed to evaluate JavaScript: {}", e))
-        })?;
+        .await
+        .unwrap();
+        install_server_from_file(&zip_path, None, install_path.clone(), true, &preview)
+            .await
+            .unwrap();
+
+        assert!(install_path.join("1.21.84.1").is_dir());
+        assert!(install_path.join("1.21.84.1-preview").is_dir());
+    }
 
-    if let Some(url) = result.value {
-        if let Some(url_str) = url.as_str() {
-            println!("Found download URL: {}", url_str);
+    #[tokio::test]
+    async fn test_install_shares_files_with_installed_versions() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = server_zip(dir.path(), "source.zip");
+        let install_path = dir.path().join("server");
+        let preview = DownloadOptions {
+            channel: Channel::Preview,
+            ..Default::default()
+        };
+
+        let release_version = install_server_from_file(
+            &zip_path,
+            Some("1.21.84.1"),
+            install_path.clone(),
+            true,
+            &DownloadOptions::default(),
+        )
+        .await
+        .unwrap();
+        let preview_version = install_server_from_file(
+            &zip_path,
+            Some("1.21.84.1"),
+            install_path.clone(),
+            true,
+            &preview,
+        )
+        .await
+        .unwrap();
+        assert_eq!(release_version, preview_version);
+
+        let inode = |name: &str, file: &str| {
+            std::fs::metadata(install_path.join(name).join(file))
+                .unwrap()
+                .ino()
+        };
+        assert_eq!(
+            inode("1.21.84.1", "bedrock_server"),
+            inode("1.21.84.1-preview", "bedrock_server")
+        );
+        assert_ne!(
+            inode("1.21.84.1", "server.properties"),
+            inode("1.21.84.1-preview", "server.properties")
+        );
+        assert!(
+            verify_install(&install_path.join("1.21.84.1"))
+                .unwrap()
+                .is_ok()
+        );
+        assert!(
+            verify_install(&install_path.join("1.21.84.1-preview"))
+                .unwrap()
+                .is_ok()
+        );
+    }
 
-            // Extract version from URL
-            let version_re = Regex::new(r"bedrock-server-(\d+\.\d+\.\d+\.\d+)\.zip").unwrap();
-            if let Some(captures) = version_re.captures(url_str) {
-                if let Some(version) = captures.get(1) {
-                    println!("Extracted version: {}", version.as_str());
-                    return Ok(version.as_str().to_string());
-                }
-            }
-        }
+    #[tokio::test]
+    async fn test_install_from_file_infers_version_from_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = server_zip(dir.path(), "bedrock-server-1.21.84.1.zip");
+        let install_path = dir.path().join("server");
+
+        let version = install_server_from_file(
+            &zip_path,
+            None,
+            install_path.clone(),
+            true,
+            &DownloadOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(version, "1.21.84.1");
+        let manifest = InstallManifest::read(&install_path.join("1.21.84.1")).unwrap();
+        assert!(manifest.source_url.starts_with("file://"));
+        assert!(
+            verify_install(&install_path.join("1.21.84.1"))
+                .unwrap()
+                .is_ok()
+        );
+        assert!(zip_path.exists());
     }
 
-    // If direct link search didn't work, try to get the page content after JS execution
-    let html = tab.get_content().map_err(|e| {
-        ServerDownloadError::DownloadFailed(format!("Failed to get page content: {}", e))
-    })?;
+    #[tokio::test]
+    async fn test_install_from_file_detects_version_from_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = server_zip(dir.path(), "server.zip");
+        let install_path = dir.path().join("server");
+
+        let version = install_server_from_file(
+            &zip_path,
+            None,
+            install_path.clone(),
+            true,
+            &DownloadOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(version, "1.21.84.1");
+        let version_path = install_path.join("1.21.84.1");
+        assert!(version_path.join("bedrock_server").exists());
+        assert!(!version_path.join("worlds").exists());
+        assert_eq!(
+            std::fs::read(version_path.join("server.properties")).unwrap(),
+            b"server-name=test\n"
+        );
+        assert!(verify_install(&version_path).unwrap().is_ok());
+        assert_eq!(
+            InstallManifest::read(&version_path).unwrap().server_version,
+            Some("1.21.84.1".to_string())
+        );
+        let leftovers: Vec<_> = std::fs::read_dir(&install_path)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains(".detect-"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
 
-    println!("Searching rendered HTML for version...");
+    #[tokio::test]
+    async fn test_install_verifies_reported_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = server_zip(dir.path(), "server.zip");
+        let install_path = dir.path().join("server");
+        let options = DownloadOptions {
+            verify_version: true,
+            ..Default::default()
+        };
+
+        let result = install_server_from_file(
+            &zip_path,
+            Some("1.21.90.1"),
+            install_path.clone(),
+            true,
+            &options,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(ServerDownloadError::VersionMismatch { expected, actual })
+                if expected == "1.21.90.1" && actual == "1.21.84.1"
+        ));
+        assert!(!install_path.join("1.21.90.1").exists());
+
+        install_server_from_file(
+            &zip_path,
+            Some("1.21.84.1"),
+            install_path.clone(),
+            true,
+            &options,
+        )
+        .await
+        .unwrap();
+        let version_path = install_path.join("1.21.84.1");
+        assert!(!version_path.join("worlds").exists());
+        assert_eq!(
+            InstallManifest::read(&version_path).unwrap().server_version,
+            Some("1.21.84.1".to_string())
+        );
+    }
 
-    // Search for version in the rendered HTML
-    let patterns = [
-        r"bedrock-server-(\d+\.\d+\.\d+\.\d+)\.zip",
-        r#"bedrockdedicatedserver/bin-linux/bedrock-server-(\d+\.\d+\.\d+\.\d+)"#,
-    ];
+    #[tokio::test]
+    async fn test_cancelled_install_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = server_zip(dir.path(), "bedrock-server-1.21.84.1.zip");
+        let install_path = dir.path().join("server");
+        let options = DownloadOptions::default();
+        options.cancel.cancel();
+
+        let result =
+            install_server_from_file(&zip_path, None, install_path.clone(), true, &options).await;
+
+        assert!(matches!(result, Err(ServerDownloadError::Cancelled)));
+        assert_eq!(std::fs::read_dir(&install_path).unwrap().count(), 0);
+        assert!(matches!(
+            get_latest_version_cancellable(Channel::Release, &options.cancel).await,
+            Err(ServerDownloadError::Cancelled)
+        ));
+    }
 
-    for pattern in patterns {
-        let re = Regex::new(pattern).unwrap();
-        if let Some(captures) = re.captures(&html) {
-            if let Some(version) = captures.get(1) {
-                println!("Found version: {}", version.as_str());
-                return Ok(version.as_str().to_string());
+    #[tokio::test]
+    async fn test_install_cancelled_during_version_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_path = dir.path().join("pid");
+        // Never reports a version, so detection only ends when cancelled
+        let script = FakeServer::new()
+            .run(&format!("echo $$ > '{}'", pid_path.display()))
+            .run("exec sleep 30")
+            .script();
+        let zip_path = dir.path().join("server.zip");
+        write_zip(&zip_path, &[("bedrock_server", script.as_bytes(), 0o755)]);
+        let install_path = dir.path().join("server");
+        let options = DownloadOptions::default();
+
+        let install = tokio::spawn({
+            let install_path = install_path.clone();
+            let options = options.clone();
+            async move { install_server_from_file(&zip_path, None, install_path, true, &options).await }
+        });
+        while !pid_path.exists() || std::fs::read_to_string(&pid_path).unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        options.cancel.cancel();
+        let result = install.await.unwrap();
+
+        assert!(matches!(result, Err(ServerDownloadError::Cancelled)));
+        assert_eq!(std::fs::read_dir(&install_path).unwrap().count(), 0);
+        // The killed server may linger briefly as a zombie until it is reaped
+        let stat = Path::new("/proc")
+            .join(std::fs::read_to_string(&pid_path).unwrap().trim())
+            .join("stat");
+        for _ in 0..50 {
+            match std::fs::read_to_string(&stat) {
+                Ok(stat) if !stat.contains(") Z ") => {
+                    tokio::time::sleep(Duration::from_millis(20)).await
+                }
+                _ => return,
             }
         }
+        panic!("server process survived the cancelled install");
     }
 
-    Err(ServerDownloadError::DownloadFailed(
-        "Could not find version in download page".to_string(),
-    ))
-}
+    #[tokio::test]
+    async fn test_install_from_file_checksum_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = server_zip(dir.path(), "bedrock-server-1.21.84.1.zip");
+        let options = DownloadOptions {
+            expected_sha256: Some("00".repeat(32)),
+            ..Default::default()
+        };
+
+        let result =
+            install_server_from_file(&zip_path, None, dir.path().join("server"), true, &options)
+                .await;
+
+        assert!(matches!(
+            result,
+            Err(ServerDownloadError::ChecksumMismatch { .. })
+        ));
+        assert!(!dir.path().join("server/1.21.84.1").exists());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_download_from_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = std::fs::read(server_zip(dir.path(), "source.zip")).unwrap();
+        let server = serve(move |_| TestResponse::new("200 OK", zip.clone())).await;
+        let install_path = dir.path().join("server");
+        let options = DownloadOptions {
+            base_url: Some(server.url("/bedrock")),
+            ..Default::default()
+        };
+
+        download_server("1.21.84.1", install_path.clone(), true, &options)
+            .await
+            .unwrap();
+
+        let manifest = InstallManifest::read(&install_path.join("1.21.84.1")).unwrap();
+        assert_eq!(
+            manifest.source_url,
+            server.url("/bedrock/bedrock-server-1.21.84.1.zip")
+        );
+        let leftovers: Vec<_> = std::fs::read_dir(&install_path)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .filter(|name| name != STORE_DIR_NAME)
+            .collect();
+        assert_eq!(
+            leftovers,
+            vec!["1.21.84.1"],
+            "partial download should be removed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_missing_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = serve(|_| TestResponse::new("404 Not Found", "")).await;
+        let options = DownloadOptions {
+            base_url: Some(server.url("/bedrock")),
+            ..Default::default()
+        };
+
+        let result = download_server("9.9.9.9", dir.path().to_path_buf(), true, &options).await;
+
+        assert!(matches!(result, Err(ServerDownloadError::VersionNotFound(v)) if v == "9.9.9.9"));
+    }
 
     #[tokio::test]
+    #[ignore = "requires network access to the Minecraft download services"]
     async fn test_get_latest_version() {
-        let result = get_latest_version().await;
+        let result = get_latest_version(Channel::Release).await;
 
         match result {
             Ok(version) => {
@@ -0,0 +1,171 @@
+//! Test fixtures: a minimal local HTTP server standing in for remote endpoints, and zip builders.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// A request received by the test server.
+pub struct TestRequest {
+    /// Zero-based index of this request among all requests the server received.
+    pub index: usize,
+    /// The request target, e.g. `/path` or `http://host/path` for requests sent to a proxy.
+    pub target: String,
+    headers: Vec<(String, String)>,
+}
+
+impl TestRequest {
+    /// Returns the value of a header, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A canned response returned by the test server.
+pub struct TestResponse {
+    pub status: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Close the connection after sending this many body bytes, simulating a dropped transfer.
+    pub truncate_at: Option<usize>,
+}
+
+impl TestResponse {
+    pub fn new(status: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+            truncate_at: None,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn truncate_at(mut self, bytes: usize) -> Self {
+        self.truncate_at = Some(bytes);
+        self
+    }
+}
+
+/// Handle to a running test server.
+pub struct TestServer {
+    pub base_url: String,
+    requests: Arc<AtomicUsize>,
+}
+
+impl TestServer {
+    /// Returns the URL for the given path on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Returns how many requests the server has received so far.
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// Starts a server on a local port that answers every request using `handler`.
+pub async fn serve<F>(handler: F) -> TestServer
+where
+    F: Fn(&TestRequest) -> TestResponse + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let handler = Arc::new(handler);
+
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let handler = handler.clone();
+            let counter = counter.clone();
+            tokio::spawn(async move {
+                let Some(head) = read_head(&mut socket).await else {
+                    return;
+                };
+                let headers = head
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                    .collect();
+                let target = head
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_string();
+                let request = TestRequest {
+                    index: counter.fetch_add(1, Ordering::SeqCst),
+                    target,
+                    headers,
+                };
+
+                let response = handler(&request);
+                let mut head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (key, value) in &response.headers {
+                    head.push_str(&format!("{}: {}\r\n", key, value));
+                }
+                head.push_str("\r\n");
+
+                let body_len = response
+                    .truncate_at
+                    .unwrap_or(response.body.len())
+                    .min(response.body.len());
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(&response.body[..body_len]).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+
+    TestServer {
+        base_url: format!("http://{}", addr),
+        requests,
+    }
+}
+
+/// Reads the request line and headers, up to the blank line that ends them.
+async fn read_head(socket: &mut tokio::net::TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = socket.read(&mut buf).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    Some(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Writes a zip at `path` containing `(name, content, unix mode)` entries.
+///
+/// Names ending in `/` are added as directories and their content is ignored.
+pub fn write_zip(path: &Path, entries: &[(&str, &[u8], u32)]) {
+    let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+    for (name, content, mode) in entries {
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(*mode);
+        if name.ends_with('/') {
+            writer.add_directory(*name, options).unwrap();
+            continue;
+        }
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap();
+}
@@ -0,0 +1,458 @@
+use crate::download::fetch::http_client;
+use crate::download::network::network_config;
+use crate::download::offline::ensure_online;
+use crate::download::server::ServerDownloadError;
+use crate::error::display_chain;
+use crate::report::{Event, report};
+use crate::server::Channel;
+#[cfg(feature = "browser-lookup")]
+use headless_chrome::{Browser, LaunchOptions};
+use regex::Regex;
+use serde::Deserialize;
+use std::future::Future;
+
+/// Default endpoint listing the current Bedrock download links as JSON.
+pub const DEFAULT_VERSION_ENDPOINT: &str =
+    "https://net-secondary.web.minecraft-services.net/api/v1.0/download/links";
+
+/// Environment variable overriding the endpoint used by [`HttpVersionSource::default`].
+pub const VERSION_ENDPOINT_ENV: &str = "BEDROCK_VERSION_URL";
+
+const VERSION_PATTERN: &str = r"bedrock-server-(\d+\.\d+\.\d+\.\d+)\.zip";
+
+/// A source that can resolve the latest available Bedrock Dedicated Server version.
+pub trait VersionSource {
+    /// Returns the latest available version string (e.g. `1.21.84.1`) in the given channel.
+    fn latest_version(
+        &self,
+        channel: Channel,
+    ) -> impl Future<Output = Result<String, ServerDownloadError>> + Send;
+}
+
+/// Returns the `downloadType` of the Linux server in the download links API.
+fn linux_download_type(channel: Channel) -> &'static str {
+    match channel {
+        Channel::Release => "serverBedrockLinux",
+        Channel::Preview => "serverBedrockPreviewLinux",
+    }
+}
+
+/// Returns the path segment of the Linux server zips on minecraft.net.
+pub(crate) fn linux_artifact_dir(channel: Channel) -> &'static str {
+    match channel {
+        Channel::Release => "bin-linux",
+        Channel::Preview => "bin-linux-preview",
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LinksResponse {
+    result: LinksResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinksResult {
+    links: Vec<DownloadLink>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadLink {
+    download_type: String,
+    download_url: String,
+}
+
+/// Resolves the latest version from a JSON endpoint listing the download links.
+///
+/// The endpoint is expected to return `{"result": {"links": [{"downloadType": ..., "downloadUrl": ...}]}}`,
+/// the same shape as the official Minecraft services API. It can be pointed at a local stand-in.
+#[derive(Debug, Clone)]
+pub struct HttpVersionSource {
+    endpoint: String,
+}
+
+impl HttpVersionSource {
+    /// Creates a source querying the given endpoint URL.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+        }
+    }
+
+    /// Returns the endpoint URL this source queries.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+impl Default for HttpVersionSource {
+    /// Uses the `BEDROCK_VERSION_URL` environment variable, or the official endpoint if unset.
+    fn default() -> Self {
+        let endpoint = std::env::var(VERSION_ENDPOINT_ENV)
+            .unwrap_or_else(|_| DEFAULT_VERSION_ENDPOINT.to_string());
+        Self::new(endpoint)
+    }
+}
+
+impl VersionSource for HttpVersionSource {
+    async fn latest_version(&self, channel: Channel) -> Result<String, ServerDownloadError> {
+        ensure_online("look up the latest server version")?;
+        report(Event::LookupProgress(format!(
+            "Querying {} for latest version...",
+            self.endpoint
+        )));
+
+        let client =
+            http_client(&network_config()).map_err(ServerDownloadError::NetworkConfigInvalid)?;
+        let request_failed = |source| ServerDownloadError::LookupRequestFailed {
+            url: self.endpoint.clone(),
+            source,
+        };
+        let body = client
+            .get(&self.endpoint)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(request_failed)?
+            .text()
+            .await
+            .map_err(request_failed)?;
+        let links: LinksResponse = serde_json::from_str(&body).map_err(|source| {
+            ServerDownloadError::LookupResponseInvalid {
+                url: self.endpoint.clone(),
+                source,
+            }
+        })?;
+
+        let download_type = linux_download_type(channel);
+        let link = links
+            .result
+            .links
+            .iter()
+            .find(|link| link.download_type == download_type)
+            .ok_or_else(|| {
+                ServerDownloadError::VersionLookupFailed(format!(
+                    "No {} link in response",
+                    download_type
+                ))
+            })?;
+
+        let version = version_from_url(&link.download_url).ok_or_else(|| {
+            ServerDownloadError::VersionLookupFailed(format!(
+                "Could not find version in download URL {}",
+                link.download_url
+            ))
+        })?;
+        report(Event::LookupProgress(format!("Found version: {}", version)));
+
+        Ok(version)
+    }
+}
+
+/// Resolves the latest version by rendering the Minecraft download page in headless Chrome.
+///
+/// Requires Chrome or Chromium to be installed. Slow and sensitive to page changes, so it is
+/// only used as a fallback by [`get_latest_version`](crate::download::server::get_latest_version).
+/// Requires the `browser-lookup` feature.
+#[cfg(feature = "browser-lookup")]
+#[derive(Debug, Clone, Default)]
+pub struct BrowserVersionSource;
+
+#[cfg(feature = "browser-lookup")]
+impl VersionSource for BrowserVersionSource {
+    async fn latest_version(&self, channel: Channel) -> Result<String, ServerDownloadError> {
+        ensure_online("look up the latest server version")?;
+        report(Event::LookupProgress(
+            "Launching headless browser...".to_string(),
+        ));
+
+        // Route the browser through the same proxy as downloads. Chrome uses its own certificate
+        // store, so the CA bundle does not apply here.
+        let network = network_config();
+        let proxy_args: Vec<String> = network
+            .browser_proxy()?
+            .map(|proxy| format!("--proxy-server={}", proxy))
+            .into_iter()
+            .chain(
+                network
+                    .browser_proxy_bypass()
+                    .map(|hosts| format!("--proxy-bypass-list={}", hosts)),
+            )
+            .collect();
+
+        // Launch headless Chrome with options to handle HTTP/2 issues
+        let mut args = vec![
+            std::ffi::OsStr::new("--disable-http2"),
+            std::ffi::OsStr::new(
+                "--user-agent=Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            ),
+            std::ffi::OsStr::new("--disable-blink-features=AutomationControlled"),
+            std::ffi::OsStr::new("--no-sandbox"),
+            std::ffi::OsStr::new("--disable-dev-shm-usage"),
+        ];
+        args.extend(proxy_args.iter().map(std::ffi::OsStr::new));
+        let launch_options = LaunchOptions {
+            headless: true,
+            args,
+            ..Default::default()
+        };
+
+        let browser = Browser::new(launch_options).map_err(browser_failed("launch the browser"))?;
+
+        // Navigate to the download page
+        let tab = browser
+            .new_tab()
+            .map_err(browser_failed("create a new tab"))?;
+
+        tab.navigate_to("https://minecraft.net/en-us/download/server/bedrock/")
+            .map_err(browser_failed("navigate to the download page"))?;
+
+        // Wait for the page to load and JavaScript to execute
+        report(Event::LookupProgress(
+            "Waiting for page to load...".to_string(),
+        ));
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+
+        // Try to find the download button/link for Linux server
+        let artifact_dir = linux_artifact_dir(channel);
+        let result = tab
+            .evaluate(
+                &format!(
+                    r#"
+                    // Find all links with href containing bedrock-server
+                    const links = Array.from(document.querySelectorAll('a[href*="bedrock-server"]'));
+                    const linuxLink = links.find(link => link.href.includes('/{}/'));
+                    if (linuxLink) {{
+                        linuxLink.href;
+                    }} else {{
+                        // Try to find by aria-label
+                        const button = document.querySelector('a[aria-label="{}"]');
+                        button ? button.href : null;
+                    }}
+                    "#,
+                    artifact_dir,
+                    linux_download_type(channel)
+                ),
+                false,
+            )
+            .map_err(browser_failed("evaluate JavaScript"))?;
+
+        if let Some(url_str) = result.value.as_ref().and_then(|url| url.as_str()) {
+            report(Event::LookupProgress(format!(
+                "Found download URL: {}",
+                url_str
+            )));
+
+            if let Some(version) = version_from_url(url_str) {
+                report(Event::LookupProgress(format!(
+                    "Extracted version: {}",
+                    version
+                )));
+                return Ok(version);
+            }
+        }
+
+        // If direct link search didn't work, try to get the page content after JS execution
+        let html = tab
+            .get_content()
+            .map_err(browser_failed("get the page content"))?;
+
+        report(Event::LookupProgress(
+            "Searching rendered HTML for version...".to_string(),
+        ));
+
+        // Search for version in the rendered HTML
+        let pattern = format!(
+            r#"bedrockdedicatedserver/{}/bedrock-server-(\d+\.\d+\.\d+\.\d+)"#,
+            artifact_dir
+        );
+        let mut patterns = vec![pattern.as_str()];
+        if channel == Channel::Release {
+            patterns.push(VERSION_PATTERN);
+        }
+
+        for pattern in patterns {
+            let re = Regex::new(pattern).unwrap();
+            if let Some(version) = re.captures(&html).and_then(|captures| captures.get(1)) {
+                report(Event::LookupProgress(format!(
+                    "Found version: {}",
+                    version.as_str()
+                )));
+                return Ok(version.as_str().to_string());
+            }
+        }
+
+        Err(ServerDownloadError::VersionLookupFailed(
+            "Could not find version in download page".to_string(),
+        ))
+    }
+}
+
+#[cfg(feature = "browser-lookup")]
+fn browser_failed<E>(action: &'static str) -> impl FnOnce(E) -> ServerDownloadError
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    move |e| ServerDownloadError::BrowserFailed {
+        action,
+        source: e.into(),
+    }
+}
+
+/// Tries a primary source and falls back to a secondary one if it fails.
+#[derive(Debug, Clone, Default)]
+pub struct FallbackVersionSource<P, F> {
+    primary: P,
+    fallback: F,
+}
+
+impl<P, F> FallbackVersionSource<P, F> {
+    /// Creates a source that queries `primary` first and `fallback` if it fails.
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+impl<P, F> VersionSource for FallbackVersionSource<P, F>
+where
+    P: VersionSource + Sync,
+    F: VersionSource + Sync,
+{
+    async fn latest_version(&self, channel: Channel) -> Result<String, ServerDownloadError> {
+        match self.primary.latest_version(channel).await {
+            Ok(version) => Ok(version),
+            // The fallback can't do any better without the network
+            Err(e @ ServerDownloadError::Offline(_)) => Err(e),
+            Err(e) => {
+                report(Event::LookupFallback(display_chain(&e)));
+                self.fallback.latest_version(channel).await
+            }
+        }
+    }
+}
+
+/// Extracts the version from a `bedrock-server-<version>.zip` download URL or file name.
+pub(crate) fn version_from_url(url: &str) -> Option<String> {
+    let version_re = Regex::new(VERSION_PATTERN).unwrap();
+    version_re
+        .captures(url)
+        .and_then(|captures| captures.get(1))
+        .map(|version| version.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::test_support::{TestResponse, serve};
+
+    /// Serves `body` with the given status for every request and returns the endpoint URL.
+    async fn serve_json(status: &'static str, body: &'static str) -> String {
+        let server = serve(move |_| {
+            TestResponse::new(status, body).header("Content-Type", "application/json")
+        })
+        .await;
+        server.url("/api/v1.0/download/links")
+    }
+
+    #[tokio::test]
+    async fn test_http_source_parses_links() {
+        let url = serve_json(
+            "200 OK",
+            r#"{"result":{"links":[
+                {"downloadType":"serverBedrockWindows","downloadUrl":"https://www.minecraft.net/bedrockdedicatedserver/bin-win/bedrock-server-1.21.90.3.zip"},
+                {"downloadType":"serverBedrockLinux","downloadUrl":"https://www.minecraft.net/bedrockdedicatedserver/bin-linux/bedrock-server-1.21.90.3.zip"}
+            ]}}"#,
+        )
+        .await;
+
+        let version = HttpVersionSource::new(url)
+            .latest_version(Channel::Release)
+            .await
+            .unwrap();
+        assert_eq!(version, "1.21.90.3");
+    }
+
+    #[tokio::test]
+    async fn test_http_source_preview_channel() {
+        let url = serve_json(
+            "200 OK",
+            r#"{"result":{"links":[
+                {"downloadType":"serverBedrockLinux","downloadUrl":"https://www.minecraft.net/bedrockdedicatedserver/bin-linux/bedrock-server-1.21.90.3.zip"},
+                {"downloadType":"serverBedrockPreviewLinux","downloadUrl":"https://www.minecraft.net/bedrockdedicatedserver/bin-linux-preview/bedrock-server-1.21.100.20.zip"}
+            ]}}"#,
+        )
+        .await;
+
+        let version = HttpVersionSource::new(url)
+            .latest_version(Channel::Preview)
+            .await
+            .unwrap();
+        assert_eq!(version, "1.21.100.20");
+    }
+
+    #[tokio::test]
+    async fn test_http_source_missing_linux_link() {
+        let url = serve_json("200 OK", r#"{"result":{"links":[]}}"#).await;
+
+        let result = HttpVersionSource::new(url)
+            .latest_version(Channel::Release)
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServerDownloadError::VersionLookupFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_http_source_error_status() {
+        let url = serve_json("503 Service Unavailable", "{}").await;
+
+        let result = HttpVersionSource::new(url)
+            .latest_version(Channel::Release)
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServerDownloadError::LookupRequestFailed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_source_used_on_failure() {
+        let primary = serve_json("404 Not Found", "{}").await;
+        let fallback = serve_json(
+            "200 OK",
+            r#"{"result":{"links":[{"downloadType":"serverBedrockLinux","downloadUrl":"https://example.com/bedrock-server-1.21.84.1.zip"}]}}"#,
+        )
+        .await;
+
+        let source = FallbackVersionSource::new(
+            HttpVersionSource::new(primary),
+            HttpVersionSource::new(fallback),
+        );
+        assert_eq!(
+            source.latest_version(Channel::Release).await.unwrap(),
+            "1.21.84.1"
+        );
+    }
+
+    struct OfflineSource;
+
+    impl VersionSource for OfflineSource {
+        async fn latest_version(&self, _channel: Channel) -> Result<String, ServerDownloadError> {
+            Err(ServerDownloadError::Offline("look up".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fallback_source_not_used_offline() {
+        let fallback = serve(|_| TestResponse::new("200 OK", "{}")).await;
+
+        let source =
+            FallbackVersionSource::new(OfflineSource, HttpVersionSource::new(fallback.url("/")));
+        assert!(matches!(
+            source.latest_version(Channel::Release).await,
+            Err(ServerDownloadError::Offline(_))
+        ));
+        assert_eq!(fallback.request_count(), 0);
+    }
+}
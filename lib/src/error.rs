@@ -0,0 +1,214 @@
+#[cfg(feature = "cache")]
+use crate::cache::CacheError;
+use crate::content_log::ContentLogError;
+use crate::dedup::DedupError;
+#[cfg(feature = "download")]
+use crate::download::server::ServerDownloadError;
+use crate::instance::InstanceError;
+use crate::integrity::IntegrityError;
+use crate::lock::LockError;
+use crate::properties::PropertiesError;
+use crate::server::ServerError;
+use crate::server_path::ServerPathError;
+use crate::snapshot::SnapshotError;
+use crate::validate::ValidationError;
+use crate::world::WorldError;
+
+/// The broad category of a library error, e.g. to choose a process exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The request itself was wrong, e.g. a version that isn't installed or a missing pack.
+    Usage,
+    /// The packs were loaded, but failed validation.
+    Validation,
+    /// Something the caller can't fix by changing the request failed, e.g. the network, the
+    /// file system or the server process.
+    Infrastructure,
+    /// The operation was cancelled through its cancellation token.
+    Cancelled,
+}
+
+impl ErrorKind {
+    /// Classifies an error by the first library error in its source chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - Any error, possibly wrapping a library error as its source
+    ///
+    /// # Returns
+    ///
+    /// * `Some(ErrorKind)` - The category of the outermost library error in the chain
+    /// * `None` - If the chain contains no library error
+    pub fn of(error: &(dyn std::error::Error + 'static)) -> Option<ErrorKind> {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(kind) = Error::kind_of(error) {
+                return Some(kind);
+            }
+            current = error.source();
+        }
+        None
+    }
+}
+
+/// Any error returned by the library.
+///
+/// Every module has its own error type, which converts into this one, so callers working with
+/// several modules can use a single error type.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    ServerPath(#[from] ServerPathError),
+    #[error(transparent)]
+    Server(#[from] ServerError),
+    #[error(transparent)]
+    Instance(#[from] InstanceError),
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+    #[error(transparent)]
+    Dedup(#[from] DedupError),
+    #[error(transparent)]
+    Lock(#[from] LockError),
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+    #[error(transparent)]
+    World(#[from] WorldError),
+    #[error(transparent)]
+    Properties(#[from] PropertiesError),
+    #[error(transparent)]
+    ContentLog(#[from] ContentLogError),
+    #[cfg(feature = "download")]
+    #[error(transparent)]
+    Download(#[from] ServerDownloadError),
+    #[cfg(feature = "cache")]
+    #[error(transparent)]
+    Cache(#[from] CacheError),
+}
+
+impl Error {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::ServerPath(e) => e.kind(),
+            Error::Server(e) => e.kind(),
+            Error::Instance(e) => e.kind(),
+            Error::Validation(e) => e.kind(),
+            Error::Integrity(e) => e.kind(),
+            Error::Dedup(e) => e.kind(),
+            Error::Lock(e) => e.kind(),
+            Error::Snapshot(e) => e.kind(),
+            Error::World(e) => e.kind(),
+            Error::Properties(e) => e.kind(),
+            Error::ContentLog(e) => e.kind(),
+            #[cfg(feature = "download")]
+            Error::Download(e) => e.kind(),
+            #[cfg(feature = "cache")]
+            Error::Cache(e) => e.kind(),
+        }
+    }
+
+    /// Returns the category of `error` if it is one of the library's error types.
+    fn kind_of(error: &(dyn std::error::Error + 'static)) -> Option<ErrorKind> {
+        if let Some(e) = error.downcast_ref::<Error>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<ServerPathError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<ServerError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<InstanceError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<ValidationError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<IntegrityError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<DedupError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<LockError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<SnapshotError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<WorldError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<PropertiesError>() {
+            return Some(e.kind());
+        }
+        if let Some(e) = error.downcast_ref::<ContentLogError>() {
+            return Some(e.kind());
+        }
+        #[cfg(feature = "download")]
+        if let Some(e) = error.downcast_ref::<ServerDownloadError>() {
+            return Some(e.kind());
+        }
+        #[cfg(feature = "cache")]
+        if let Some(e) = error.downcast_ref::<CacheError>() {
+            return Some(e.kind());
+        }
+        None
+    }
+}
+
+/// Formats an error followed by each of its sources, separated by colons, for messages that
+/// are reported as plain text.
+#[cfg(any(feature = "download", feature = "cache"))]
+pub(crate) fn display_chain(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = error.to_string();
+    let mut current = error.source();
+    while let Some(source) = current {
+        message.push_str(": ");
+        message.push_str(&source.to_string());
+        current = source.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Command failed")]
+    struct CommandFailed(#[source] ValidationError);
+
+    #[test]
+    fn test_kind_follows_the_source_chain() {
+        let error = ValidationError::from(ServerError::from(ServerPathError::CreateFailed {
+            path: PathBuf::from("/nonexistent"),
+            source: std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        }));
+        assert_eq!(error.kind(), ErrorKind::Infrastructure);
+        assert_eq!(Error::from(error).kind(), ErrorKind::Infrastructure);
+
+        let error = ServerError::NotInstalled("1.21.84.1".to_string());
+        assert_eq!(ErrorKind::of(&error), Some(ErrorKind::Usage));
+
+        let error = CommandFailed(ValidationError::ValidationFailed(
+            "Server exited before loading the packs".to_string(),
+        ));
+        assert_eq!(ErrorKind::of(&error), Some(ErrorKind::Validation));
+        assert_eq!(ErrorKind::of(&std::io::Error::other("unrelated")), None);
+    }
+
+    #[cfg(any(feature = "download", feature = "cache"))]
+    #[test]
+    fn test_display_chain_includes_sources() {
+        let error = ServerPathError::CreateFailed {
+            path: PathBuf::from("/nonexistent"),
+            source: std::io::Error::other("disk full"),
+        };
+        assert_eq!(display_chain(&error), format!("{}: disk full", error));
+    }
+}
@@ -0,0 +1,66 @@
+//! Filesystem helpers shared by several modules.
+
+use std::fs;
+use std::path::Path;
+
+/// Recursively copies the directory `from` to `to`, following symlinks and keeping file
+/// permissions.
+///
+/// # Arguments
+///
+/// * `from` - The directory to copy
+/// * `to` - The directory to copy into, created if it doesn't exist
+/// * `copy_failed` - Builds the error for an I/O failure on the given path
+pub(crate) fn copy_dir<E>(
+    from: &Path,
+    to: &Path,
+    copy_failed: &impl Fn(&Path, std::io::Error) -> E,
+) -> Result<(), E> {
+    fs::create_dir_all(to).map_err(|source| copy_failed(to, source))?;
+    for entry in fs::read_dir(from).map_err(|source| copy_failed(from, source))? {
+        let entry = entry.map_err(|source| copy_failed(from, source))?;
+        let source_path = entry.path();
+        let dest = to.join(entry.file_name());
+        if source_path.is_dir() {
+            copy_dir(&source_path, &dest, copy_failed)?;
+        } else {
+            fs::copy(&source_path, &dest).map_err(|source| copy_failed(&source_path, source))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    #[test]
+    fn test_copy_dir_follows_symlinks_and_keeps_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from");
+        let linked = dir.path().join("linked");
+        fs::create_dir_all(from.join("nested")).unwrap();
+        fs::create_dir_all(&linked).unwrap();
+        fs::write(from.join("nested/run.sh"), b"#!/bin/sh").unwrap();
+        fs::set_permissions(
+            from.join("nested/run.sh"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        fs::write(linked.join("file.txt"), b"linked").unwrap();
+        symlink(&linked, from.join("link")).unwrap();
+
+        let to = dir.path().join("to");
+        copy_dir(&from, &to, &|_, source| source).unwrap();
+
+        let mode = fs::metadata(to.join("nested/run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert!(!fs::symlink_metadata(to.join("link")).unwrap().is_symlink());
+        assert_eq!(fs::read(to.join("link/file.txt")).unwrap(), b"linked");
+    }
+}
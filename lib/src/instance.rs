@@ -0,0 +1,353 @@
+use crate::error::ErrorKind;
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc;
+
+/// How long [`ServerInstance::stop`] waits for the server to exit before killing it.
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum InstanceError {
+    #[error("Invalid server path: {0}")]
+    InvalidServerPath(String),
+    #[error("Failed to start server {}", path.display())]
+    StartFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to make server {} executable", path.display())]
+    PermissionsFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to send command to server")]
+    CommandFailed(#[source] std::io::Error),
+    #[error("Server did not log \"{pattern}\" within {seconds} seconds")]
+    Timeout { pattern: String, seconds: u64 },
+    #[error("Server exited before logging \"{0}\"")]
+    Exited(String),
+    #[error("Failed to stop server")]
+    StopFailed(#[source] std::io::Error),
+}
+
+impl InstanceError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            InstanceError::InvalidServerPath(_) => ErrorKind::Usage,
+            _ => ErrorKind::Infrastructure,
+        }
+    }
+}
+
+/// A running Bedrock Dedicated Server process.
+///
+/// The server's stdout and stderr are merged into a single stream of log lines, read with
+/// [`ServerInstance::next_line`] or [`ServerInstance::wait_for`]. Dropping the instance kills
+/// the server, so it never outlives its owner.
+#[derive(Debug)]
+pub struct ServerInstance {
+    path: PathBuf,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: mpsc::UnboundedReceiver<String>,
+}
+
+impl ServerInstance {
+    /// Starts the server in `server_path`.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_path` - Path to the server directory containing bedrock_server
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ServerInstance)` - The running server
+    /// * `Err(InstanceError)` - If the server directory is invalid or the process failed to start
+    pub async fn start(server_path: &Path) -> Result<Self, InstanceError> {
+        if !server_path.is_dir() {
+            return Err(InstanceError::InvalidServerPath(format!(
+                "{} does not exist or is not a directory",
+                server_path.display()
+            )));
+        }
+
+        let server_exe = server_path.join("bedrock_server");
+        if !server_exe.exists() {
+            return Err(InstanceError::InvalidServerPath(format!(
+                "bedrock_server executable not found in {}",
+                server_path.display()
+            )));
+        }
+        make_executable(&server_exe)?;
+
+        let mut child = Command::new(&server_exe)
+            .current_dir(server_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|source| InstanceError::StartFailed {
+                path: server_exe.clone(),
+                source,
+            })?;
+
+        let not_captured = |stream| InstanceError::StartFailed {
+            path: server_exe.clone(),
+            source: std::io::Error::other(format!("Failed to capture {}", stream)),
+        };
+        let stdout = child.stdout.take().ok_or_else(|| not_captured("stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| not_captured("stderr"))?;
+
+        let (sender, lines) = mpsc::unbounded_channel();
+        forward_lines(stdout, sender.clone());
+        forward_lines(stderr, sender);
+
+        Ok(Self {
+            path: server_path.to_path_buf(),
+            stdin: child.stdin.take(),
+            child,
+            lines,
+        })
+    }
+
+    /// Returns the server directory this instance was started in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sends a console command to the server, e.g. `say hello` or `reload`.
+    pub async fn send_command(&mut self, command: &str) -> Result<(), InstanceError> {
+        let stdin = self.stdin.as_mut().ok_or_else(|| {
+            InstanceError::CommandFailed(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "stdin is closed",
+            ))
+        })?;
+        stdin
+            .write_all(format!("{}\n", command.trim_end()).as_bytes())
+            .await
+            .map_err(InstanceError::CommandFailed)?;
+        stdin.flush().await.map_err(InstanceError::CommandFailed)
+    }
+
+    /// Returns the next non-empty log line, trimmed, from either stdout or stderr.
+    ///
+    /// Returns `None` once the server has exited and all output has been read. This is
+    /// cancellation safe, so it can be used in `tokio::select!`.
+    pub async fn next_line(&mut self) -> Option<String> {
+        self.lines.recv().await
+    }
+
+    /// Reads log lines until one contains `pattern`.
+    ///
+    /// Lines read while waiting are consumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Text the line must contain, e.g. `Server started.`
+    /// * `timeout` - How long to wait for the line
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The matching line
+    /// * `Err(InstanceError)` - If the server exited or the timeout elapsed first
+    pub async fn wait_for(
+        &mut self,
+        pattern: &str,
+        timeout: Duration,
+    ) -> Result<String, InstanceError> {
+        let line = tokio::time::timeout(timeout, async {
+            while let Some(line) = self.next_line().await {
+                if line.contains(pattern) {
+                    return Some(line);
+                }
+            }
+            None
+        })
+        .await;
+
+        match line {
+            Ok(Some(line)) => Ok(line),
+            Ok(None) => Err(InstanceError::Exited(pattern.to_string())),
+            Err(_) => Err(InstanceError::Timeout {
+                pattern: pattern.to_string(),
+                seconds: timeout.as_secs(),
+            }),
+        }
+    }
+
+    /// Stops the server with the `stop` command, killing it if it hasn't exited within
+    /// [`STOP_TIMEOUT`].
+    pub async fn stop(mut self) -> Result<ExitStatus, InstanceError> {
+        // The server may already be gone, in which case waiting returns immediately
+        self.send_command("stop").await.ok();
+        self.stdin = None;
+
+        match tokio::time::timeout(STOP_TIMEOUT, self.child.wait()).await {
+            Ok(status) => status.map_err(InstanceError::StopFailed),
+            Err(_) => self.kill().await,
+        }
+    }
+
+    /// Kills the server immediately, without letting it save.
+    pub async fn kill(mut self) -> Result<ExitStatus, InstanceError> {
+        self.child.kill().await.map_err(InstanceError::StopFailed)?;
+        self.child.wait().await.map_err(InstanceError::StopFailed)
+    }
+}
+
+fn make_executable(server_exe: &Path) -> Result<(), InstanceError> {
+    let permissions_failed = |source| InstanceError::PermissionsFailed {
+        path: server_exe.to_path_buf(),
+        source,
+    };
+    let metadata = fs::metadata(server_exe).map_err(permissions_failed)?;
+    let mut permissions = metadata.permissions();
+    if permissions.mode() & 0o111 == 0o111 {
+        return Ok(());
+    }
+    permissions.set_mode(permissions.mode() | 0o111);
+
+    // Permissions are shared between hardlinks, so give this install its own copy first rather
+    // than changing the file of every version sharing it
+    let result = if metadata.nlink() > 1 {
+        let copy = server_exe.with_file_name(".bedrock_server.exec");
+        fs::copy(server_exe, &copy)
+            .and_then(|_| fs::set_permissions(&copy, permissions))
+            .and_then(|_| fs::rename(&copy, server_exe))
+            .inspect_err(|_| {
+                fs::remove_file(&copy).ok();
+            })
+    } else {
+        fs::set_permissions(server_exe, permissions)
+    };
+    result.map_err(permissions_failed)
+}
+
+fn forward_lines<R>(reader: R, sender: mpsc::UnboundedSender<String>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = line.trim();
+            if !line.is_empty() && sender.send(line.to_string()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FakeServer;
+
+    /// Writes a server that logs a warning to stderr while starting, then handles commands.
+    fn fake_server() -> tempfile::TempDir {
+        fake_server_with(
+            FakeServer::new()
+                .log("INFO", "Version: 1.21.84.1")
+                .log_stderr("WARN", "Something odd")
+                .log("INFO", "Server started.")
+                .console(),
+        )
+    }
+
+    fn fake_server_with(server: FakeServer) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        server.write(dir.path());
+        dir
+    }
+
+    #[test]
+    fn test_make_executable_breaks_hardlinks() {
+        let dir = fake_server();
+        let exe = dir.path().join("bedrock_server");
+        let shared = dir.path().join("shared");
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o644)).unwrap();
+        fs::hard_link(&exe, &shared).unwrap();
+
+        make_executable(&exe).unwrap();
+
+        assert_eq!(
+            fs::metadata(&exe).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert_eq!(
+            fs::metadata(&shared).unwrap().permissions().mode() & 0o777,
+            0o644
+        );
+        assert_eq!(fs::read(&exe).unwrap(), fs::read(&shared).unwrap());
+        assert_eq!(fs::metadata(&shared).unwrap().nlink(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_server_instance_lifecycle() {
+        let dir = fake_server();
+        let mut server = ServerInstance::start(dir.path()).await.unwrap();
+        assert_eq!(server.path(), dir.path());
+
+        let timeout = Duration::from_secs(10);
+        server.wait_for("Server started.", timeout).await.unwrap();
+        server.send_command("say hello").await.unwrap();
+        let line = server.wait_for("Received:", timeout).await.unwrap();
+        assert!(line.ends_with("Received: say hello"));
+
+        let status = server.stop().await.unwrap();
+        assert!(status.success());
+    }
+
+    #[tokio::test]
+    async fn test_server_instance_merges_stderr() {
+        let dir = fake_server();
+        let mut server = ServerInstance::start(dir.path()).await.unwrap();
+
+        let mut lines = Vec::new();
+        while lines.len() < 3 {
+            lines.push(server.next_line().await.unwrap());
+        }
+        assert!(lines.iter().any(|line| line.contains("Something odd")));
+        server.kill().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_reports_exit_and_timeout() {
+        let dir = fake_server_with(FakeServer::new().run("echo 'Starting Server'"));
+        let mut server = ServerInstance::start(dir.path()).await.unwrap();
+        assert!(matches!(
+            server
+                .wait_for("Server started.", Duration::from_secs(10))
+                .await,
+            Err(InstanceError::Exited(_))
+        ));
+
+        let dir = fake_server_with(FakeServer::new().run("exec sleep 30"));
+        let mut server = ServerInstance::start(dir.path()).await.unwrap();
+        assert!(matches!(
+            server
+                .wait_for("Server started.", Duration::from_millis(100))
+                .await,
+            Err(InstanceError::Timeout { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_start_requires_server_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            ServerInstance::start(dir.path()).await,
+            Err(InstanceError::InvalidServerPath(_))
+        ));
+    }
+}
@@ -0,0 +1,287 @@
+use crate::error::ErrorKind;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the integrity manifest written into each installed version directory.
+pub const MANIFEST_FILE_NAME: &str = ".bedrockci-manifest.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum IntegrityError {
+    #[error("No integrity manifest found in {}", .0.display())]
+    ManifestMissing(PathBuf),
+    #[error("Failed to read integrity manifest {}", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid integrity manifest {}", path.display())]
+    ManifestInvalid {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Failed to hash {}", path.display())]
+    HashFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to write integrity manifest {}", path.display())]
+    WriteFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl IntegrityError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Infrastructure
+    }
+}
+
+/// Records what was installed into a server version directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallManifest {
+    /// The server version, as named by the install directory.
+    pub version: String,
+    /// The URL the server zip was downloaded from.
+    pub source_url: String,
+    /// SHA-256 of the downloaded zip, as lowercase hex.
+    pub zip_sha256: String,
+    /// Install time in seconds since the Unix epoch.
+    pub installed_at: u64,
+    /// The version the server itself reported when launched during install, if it was checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_version: Option<String>,
+    /// SHA-256 of every installed file, keyed by path relative to the version directory.
+    pub files: BTreeMap<String, String>,
+}
+
+/// The outcome of checking an installed server against its manifest.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// Files whose contents no longer match the recorded hash.
+    pub modified: Vec<String>,
+    /// Files recorded in the manifest that no longer exist.
+    pub missing: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Returns true if no modified or missing files were found.
+    pub fn is_ok(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl InstallManifest {
+    /// Builds a manifest by hashing every file currently in `version_path`.
+    ///
+    /// # Arguments
+    ///
+    /// * `version_path` - The installed version directory
+    /// * `version` - The server version
+    /// * `source_url` - The URL the server zip was downloaded from
+    /// * `zip_sha256` - SHA-256 of the downloaded zip
+    pub fn generate(
+        version_path: &Path,
+        version: &str,
+        source_url: &str,
+        zip_sha256: &str,
+    ) -> Result<Self, IntegrityError> {
+        let mut files = BTreeMap::new();
+        hash_dir(version_path, version_path, &mut files)?;
+
+        let installed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Ok(Self {
+            version: version.to_string(),
+            source_url: source_url.to_string(),
+            zip_sha256: zip_sha256.to_string(),
+            installed_at,
+            server_version: None,
+            files,
+        })
+    }
+
+    /// Reads the manifest from an installed version directory.
+    pub fn read(version_path: &Path) -> Result<Self, IntegrityError> {
+        let manifest_path = version_path.join(MANIFEST_FILE_NAME);
+        if !manifest_path.exists() {
+            return Err(IntegrityError::ManifestMissing(version_path.to_path_buf()));
+        }
+
+        let content =
+            fs::read_to_string(&manifest_path).map_err(|source| IntegrityError::ReadFailed {
+                path: manifest_path.clone(),
+                source,
+            })?;
+        serde_json::from_str(&content).map_err(|source| IntegrityError::ManifestInvalid {
+            path: manifest_path,
+            source,
+        })
+    }
+
+    /// Writes the manifest into an installed version directory.
+    pub fn write(&self, version_path: &Path) -> Result<(), IntegrityError> {
+        let manifest_path = version_path.join(MANIFEST_FILE_NAME);
+        serde_json::to_string_pretty(self)
+            .map_err(std::io::Error::from)
+            .and_then(|content| fs::write(&manifest_path, content))
+            .map_err(|source| IntegrityError::WriteFailed {
+                path: manifest_path,
+                source,
+            })
+    }
+
+    /// Checks the files in `version_path` against this manifest.
+    ///
+    /// Only files recorded in the manifest are checked; files added later (such as worlds or
+    /// linked test packs) are ignored.
+    pub fn verify(&self, version_path: &Path) -> Result<VerifyReport, IntegrityError> {
+        let mut report = VerifyReport::default();
+
+        for (relative_path, expected_hash) in &self.files {
+            let path = version_path.join(relative_path);
+            if !path.is_file() {
+                report.missing.push(relative_path.clone());
+                continue;
+            }
+            if &hash_file(&path)? != expected_hash {
+                report.modified.push(relative_path.clone());
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Verifies an installed server version against the manifest written at install time.
+///
+/// # Arguments
+///
+/// * `version_path` - The installed version directory
+///
+/// # Returns
+///
+/// * `Ok(VerifyReport)` - The modified and missing files, if any
+/// * `Err(IntegrityError)` - If the manifest is missing or the files could not be read
+pub fn verify_install(version_path: &Path) -> Result<VerifyReport, IntegrityError> {
+    InstallManifest::read(version_path)?.verify(version_path)
+}
+
+/// Returns the SHA-256 of a file as lowercase hex.
+pub fn hash_file(path: &Path) -> Result<String, IntegrityError> {
+    let mut hasher = Sha256::new();
+    File::open(path)
+        .and_then(|mut file| std::io::copy(&mut file, &mut hasher))
+        .map_err(|source| hash_failed(path, source))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Recursively hashes the regular files under `dir`, skipping symlinks and the manifest itself.
+pub(crate) fn hash_dir(
+    root: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, String>,
+) -> Result<(), IntegrityError> {
+    let entries = fs::read_dir(dir).map_err(|source| hash_failed(dir, source))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|source| hash_failed(dir, source))?;
+        let path = entry.path();
+        let file_type = entry
+            .file_type()
+            .map_err(|source| hash_failed(&path, source))?;
+
+        if file_type.is_dir() {
+            hash_dir(root, &path, files)?;
+        } else if file_type.is_file() {
+            let relative_path = path
+                .strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            if relative_path == MANIFEST_FILE_NAME {
+                continue;
+            }
+            files.insert(relative_path, hash_file(&path)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn hash_failed(path: &Path, source: std::io::Error) -> IntegrityError {
+    IntegrityError::HashFailed {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::install_fixture;
+
+    #[test]
+    fn test_hash_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.txt");
+        fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_manifest_round_trip_and_clean_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let version_path = install_fixture(dir.path(), "1.21.84.1", "00");
+
+        let read = InstallManifest::read(&version_path).unwrap();
+        assert_eq!(read.version, "1.21.84.1");
+        assert_eq!(read.files.len(), 2);
+        assert!(read.files.contains_key("definitions/biomes.json"));
+        assert!(!read.files.contains_key(MANIFEST_FILE_NAME));
+
+        assert!(verify_install(&version_path).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_verify_detects_modified_and_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let version_path = install_fixture(dir.path(), "1.21.84.1", "00");
+
+        fs::write(version_path.join("bedrock_server"), b"patched").unwrap();
+        fs::remove_file(version_path.join("definitions/biomes.json")).unwrap();
+        fs::write(version_path.join("extra.txt"), b"ignored").unwrap();
+
+        let report = verify_install(&version_path).unwrap();
+        assert_eq!(report.modified, vec!["bedrock_server".to_string()]);
+        assert_eq!(report.missing, vec!["definitions/biomes.json".to_string()]);
+    }
+
+    #[test]
+    fn test_verify_without_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let version_path = install_fixture(dir.path(), "1.21.84.1", "00");
+        fs::remove_file(version_path.join(MANIFEST_FILE_NAME)).unwrap();
+
+        assert!(matches!(
+            verify_install(&version_path),
+            Err(IntegrityError::ManifestMissing(_))
+        ));
+    }
+}
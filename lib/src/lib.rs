@@ -2,22 +2,55 @@
 #[cfg(not(target_os = "linux"))]
 compile_error!("This crate only supports Linux");
 
+#[cfg(all(target_os = "linux", feature = "cache"))]
+pub mod cache;
 #[cfg(target_os = "linux")]
+pub mod content_log;
+#[cfg(target_os = "linux")]
+pub mod dedup;
+#[cfg(all(target_os = "linux", feature = "download"))]
 pub mod download;
 #[cfg(target_os = "linux")]
+pub mod error;
+#[cfg(target_os = "linux")]
+mod fs_util;
+#[cfg(target_os = "linux")]
+pub mod instance;
+#[cfg(target_os = "linux")]
+pub mod integrity;
+#[cfg(target_os = "linux")]
+pub mod lock;
+#[cfg(target_os = "linux")]
+pub mod properties;
+#[cfg(target_os = "linux")]
+pub mod report;
+#[cfg(target_os = "linux")]
 pub mod server;
 #[cfg(target_os = "linux")]
 pub mod server_path;
 #[cfg(target_os = "linux")]
+pub mod snapshot;
+#[cfg(all(test, target_os = "linux"))]
+mod test_support;
+#[cfg(target_os = "linux")]
 pub mod validate;
+#[cfg(target_os = "linux")]
+pub mod world;
+
+#[cfg(target_os = "linux")]
+pub use error::{Error, ErrorKind};
+
+/// Token for cancelling long-running operations such as downloads and validations.
+pub use tokio_util::sync::CancellationToken;
 
 #[cfg(target_os = "linux")]
 pub fn check_ubuntu() {
-    if let Ok(os_release) = std::fs::read_to_string("/etc/os-release") {
-        if !os_release.contains("Ubuntu") {
-            eprintln!(
-                "Warning: This crate is recommended to be run on Ubuntu. Other Linux distributions may not work as expected, but probably will."
-            );
-        }
+    if let Ok(os_release) = std::fs::read_to_string("/etc/os-release")
+        && !os_release.contains("Ubuntu")
+    {
+        report::report(report::Event::Warning(
+            "This crate is recommended to be run on Ubuntu. Other Linux distributions may not work as expected, but probably will."
+                .to_string(),
+        ));
     }
 }
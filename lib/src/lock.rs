@@ -0,0 +1,229 @@
+use crate::error::ErrorKind;
+use crate::integrity::{InstallManifest, IntegrityError, verify_install};
+use crate::server::Channel;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the lock file, kept in the project directory next to the packs.
+pub const LOCK_FILE_NAME: &str = "bedrockci.lock";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("Invalid lock file {}", path.display())]
+    Invalid {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Failed to read lock file {}", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to write lock file {}", path.display())]
+    WriteFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Locked server version {0} is not installed")]
+    NotInstalled(String),
+    #[error("Installed server does not match the lock file: {0}")]
+    Mismatch(String),
+    #[error("Failed to check installed server {name}")]
+    VerifyFailed {
+        name: String,
+        #[source]
+        source: IntegrityError,
+    },
+}
+
+impl LockError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            LockError::Invalid { .. } | LockError::NotInstalled(_) | LockError::Mismatch(_) => {
+                ErrorKind::Usage
+            }
+            // An install without a readable manifest has to be reinstalled before it can be locked
+            LockError::VerifyFailed {
+                source: IntegrityError::ManifestMissing(_) | IntegrityError::ManifestInvalid { .. },
+                ..
+            } => ErrorKind::Usage,
+            LockError::VerifyFailed { source, .. } => source.kind(),
+            LockError::ReadFailed { .. } | LockError::WriteFailed { .. } => {
+                ErrorKind::Infrastructure
+            }
+        }
+    }
+}
+
+/// Pins the exact server build a project is validated against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockFile {
+    /// The server version, without any channel suffix.
+    pub version: String,
+    pub channel: Channel,
+    /// The URL the server zip was downloaded from.
+    pub url: String,
+    /// SHA-256 of the server zip, as lowercase hex.
+    pub sha256: String,
+}
+
+impl LockFile {
+    /// Builds a lock file pinning an installed server, from its integrity manifest.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_path` - The server path holding the installed versions
+    /// * `name` - The install directory name, e.g. `1.21.84.1` or `1.21.100.20-preview`
+    pub fn from_install(server_path: &Path, name: &str) -> Result<Self, LockError> {
+        let manifest = InstallManifest::read(&server_path.join(name)).map_err(|source| {
+            LockError::VerifyFailed {
+                name: name.to_string(),
+                source,
+            }
+        })?;
+
+        Ok(Self {
+            version: manifest.version,
+            channel: Channel::from_dir_name(name).0,
+            url: manifest.source_url,
+            sha256: manifest.zip_sha256,
+        })
+    }
+
+    /// Reads a lock file, returning `None` if it doesn't exist.
+    pub fn read(path: &Path) -> Result<Option<Self>, LockError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path).map_err(|source| LockError::ReadFailed {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|source| LockError::Invalid {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Writes the lock file to `path`.
+    pub fn write(&self, path: &Path) -> Result<(), LockError> {
+        serde_json::to_string_pretty(self)
+            .map_err(std::io::Error::from)
+            .and_then(|content| fs::write(path, content + "\n"))
+            .map_err(|source| LockError::WriteFailed {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Returns the install directory name of the locked server.
+    pub fn dir_name(&self) -> String {
+        self.channel.dir_name(&self.version)
+    }
+
+    /// Checks that the locked server is installed from the locked zip and unmodified.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_path` - The server path holding the installed versions
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the installed server matches the lock file
+    /// * `Err(LockError)` - If it is missing, was installed from another zip or was modified
+    pub fn check_installed(&self, server_path: &Path) -> Result<(), LockError> {
+        let name = self.dir_name();
+        let version_path = server_path.join(&name);
+        if !version_path.is_dir() {
+            return Err(LockError::NotInstalled(name));
+        }
+
+        let installed = Self::from_install(server_path, &name)?;
+        if !installed.sha256.eq_ignore_ascii_case(&self.sha256) {
+            return Err(LockError::Mismatch(format!(
+                "{} was installed from a zip with SHA-256 {}, expected {}",
+                name, installed.sha256, self.sha256
+            )));
+        }
+
+        let report = verify_install(&version_path).map_err(|source| LockError::VerifyFailed {
+            name: name.clone(),
+            source,
+        })?;
+        if !report.is_ok() {
+            return Err(LockError::Mismatch(format!(
+                "{} has {} modified and {} missing files",
+                name,
+                report.modified.len(),
+                report.missing.len()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::install_fixture;
+
+    #[test]
+    fn test_lock_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        install_fixture(dir.path(), "1.21.100.20-preview", "abc");
+        let lock_path = dir.path().join(LOCK_FILE_NAME);
+
+        let lock = LockFile::from_install(dir.path(), "1.21.100.20-preview").unwrap();
+        lock.write(&lock_path).unwrap();
+
+        assert_eq!(LockFile::read(&lock_path).unwrap(), Some(lock.clone()));
+        assert_eq!(lock.version, "1.21.100.20");
+        assert_eq!(lock.channel, Channel::Preview);
+        assert_eq!(lock.dir_name(), "1.21.100.20-preview");
+        assert_eq!(
+            LockFile::read(&dir.path().join("missing.lock")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_installed() {
+        let dir = tempfile::tempdir().unwrap();
+        install_fixture(dir.path(), "1.21.84.1", "abc");
+        let lock = LockFile::from_install(dir.path(), "1.21.84.1").unwrap();
+        lock.check_installed(dir.path()).unwrap();
+
+        let other_zip = LockFile {
+            sha256: "def".to_string(),
+            ..lock.clone()
+        };
+        assert!(matches!(
+            other_zip.check_installed(dir.path()),
+            Err(LockError::Mismatch(_))
+        ));
+
+        let other_version = LockFile {
+            version: "1.21.9.1".to_string(),
+            ..lock.clone()
+        };
+        assert!(matches!(
+            other_version.check_installed(dir.path()),
+            Err(LockError::NotInstalled(_))
+        ));
+
+        fs::write(dir.path().join("1.21.84.1/bedrock_server"), b"patched").unwrap();
+        assert!(matches!(
+            lock.check_installed(dir.path()),
+            Err(LockError::Mismatch(_))
+        ));
+    }
+}
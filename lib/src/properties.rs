@@ -0,0 +1,344 @@
+use crate::error::ErrorKind;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Name of the server's properties file, inside an installed server directory.
+pub const SERVER_PROPERTIES_FILE_NAME: &str = "server.properties";
+
+/// The world directory the server loads, inside `worlds`.
+pub const LEVEL_NAME: &str = "level-name";
+/// The level name used when server.properties doesn't set one.
+pub const DEFAULT_LEVEL_NAME: &str = "Bedrock level";
+/// The IPv4 port the server listens on.
+pub const SERVER_PORT: &str = "server-port";
+/// Whether content log entries, such as pack errors, are printed to the console.
+pub const CONTENT_LOG_CONSOLE_OUTPUT_ENABLED: &str = "content-log-console-output-enabled";
+/// Whether content log entries are written to a file in the server's `logs` directory.
+pub const CONTENT_LOG_FILE_ENABLED: &str = "content-log-file-enabled";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PropertiesError {
+    #[error("Failed to read {}", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to write {}", path.display())]
+    WriteFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid property override \"{0}\", expected key=value")]
+    InvalidOverride(String),
+    #[error("Invalid value \"{value}\" for property {key}")]
+    InvalidValue { key: String, value: String },
+}
+
+impl PropertiesError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PropertiesError::ReadFailed { .. } | PropertiesError::WriteFailed { .. } => {
+                ErrorKind::Infrastructure
+            }
+            PropertiesError::InvalidOverride(_) | PropertiesError::InvalidValue { .. } => {
+                ErrorKind::Usage
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    Property {
+        key: String,
+        value: String,
+        /// The line as read, written back as is until the value is set.
+        raw: Option<String>,
+    },
+    /// A comment or blank line, kept as is.
+    Other(String),
+}
+
+/// The contents of a server.properties file.
+///
+/// Comments, blank lines, the order of properties and the text of unchanged lines are kept, so
+/// a file that is read, changed and written again only differs in the changed lines, apart from
+/// line endings being written as `\n`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerProperties {
+    lines: Vec<Line>,
+}
+
+impl ServerProperties {
+    /// Reads a server.properties file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the file
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ServerProperties)` - The parsed properties
+    /// * `Err(PropertiesError)` - If the file couldn't be read
+    pub fn read(path: &Path) -> Result<Self, PropertiesError> {
+        fs::read_to_string(path)
+            .map(|content| Self::parse(&content))
+            .map_err(|source| PropertiesError::ReadFailed {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Parses the contents of a server.properties file. Lines without a `=` are kept as comments.
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((key, value)) if !line.trim_start().starts_with('#') => Line::Property {
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                    raw: Some(line.to_string()),
+                },
+                _ => Line::Other(line.to_string()),
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    /// Writes the properties to `path`.
+    ///
+    /// Any existing file is replaced rather than written in place, so a file sharing its inode
+    /// with another install is never modified.
+    pub fn write(&self, path: &Path) -> Result<(), PropertiesError> {
+        let write_failed = |source| PropertiesError::WriteFailed {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(write_failed(e)),
+            _ => {}
+        }
+        fs::write(path, self.to_string()).map_err(write_failed)
+    }
+
+    /// Returns the value of `key`, if set.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Property { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the value of `key` parsed as `T`, e.g. a `u16` port or a `bool` flag.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(T))` - The parsed value
+    /// * `Ok(None)` - If `key` isn't set
+    /// * `Err(PropertiesError)` - If the value can't be parsed as `T`
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Result<Option<T>, PropertiesError> {
+        self.get(key)
+            .map(|value| {
+                value.parse().map_err(|_| PropertiesError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })
+            })
+            .transpose()
+    }
+
+    /// Sets `key` to `value`, replacing its current value or appending it to the file.
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        let value = value.to_string();
+        let existing = self.lines.iter_mut().find_map(|line| match line {
+            Line::Property { key: k, value, raw } if k == key => Some((value, raw)),
+            _ => None,
+        });
+
+        match existing {
+            Some((existing, raw)) => {
+                *existing = value;
+                *raw = None;
+            }
+            None => self.lines.push(Line::Property {
+                key: key.to_string(),
+                value,
+                raw: None,
+            }),
+        }
+    }
+
+    /// Sets every property of `overrides`, keeping all others.
+    pub fn merge(&mut self, overrides: &ServerProperties) {
+        for (key, value) in overrides.iter() {
+            self.set(key, value);
+        }
+    }
+
+    /// Returns every property in file order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Property { key, value, .. } => Some((key.as_str(), value.as_str())),
+            Line::Other(_) => None,
+        })
+    }
+
+    /// Returns the world directory the server loads, inside `worlds`.
+    pub fn level_name(&self) -> &str {
+        self.get(LEVEL_NAME)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_LEVEL_NAME)
+    }
+
+    /// Returns the IPv4 port the server listens on, if set.
+    pub fn server_port(&self) -> Result<Option<u16>, PropertiesError> {
+        self.get_parsed(SERVER_PORT)
+    }
+}
+
+impl fmt::Display for ServerProperties {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Property {
+                    raw: Some(text), ..
+                } => writeln!(f, "{}", text)?,
+                Line::Property { key, value, .. } => writeln!(f, "{}={}", key, value)?,
+                Line::Other(text) => writeln!(f, "{}", text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the server.properties of an installed server.
+///
+/// # Arguments
+///
+/// * `server_path` - Path to the directory of an installed server version
+///
+/// # Returns
+///
+/// * `Ok(ServerProperties)` - The server's properties, or empty properties if it has no file
+/// * `Err(PropertiesError)` - If the file exists but couldn't be read
+pub fn read_server_properties(server_path: &Path) -> Result<ServerProperties, PropertiesError> {
+    let path = server_path.join(SERVER_PROPERTIES_FILE_NAME);
+    if !path.exists() {
+        return Ok(ServerProperties::default());
+    }
+    ServerProperties::read(&path)
+}
+
+/// Parses a `key=value` property override, e.g. from the command line.
+///
+/// # Returns
+///
+/// * `Ok((String, String))` - The key and value
+/// * `Err(PropertiesError)` - If there is no `=` or the key is empty
+pub fn parse_override(text: &str) -> Result<(String, String), PropertiesError> {
+    match text.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(PropertiesError::InvalidOverride(text.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STOCK: &str = "server-name=Dedicated Server
+# Used as the server name
+gamemode=survival
+
+level-name=Bedrock level
+server-port=19132
+";
+
+    #[test]
+    fn test_properties_round_trip_with_changes() {
+        let mut properties = ServerProperties::parse(STOCK);
+        assert_eq!(properties.get("gamemode"), Some("survival"));
+        assert_eq!(properties.level_name(), "Bedrock level");
+        assert_eq!(properties.server_port().unwrap(), Some(19132));
+        assert_eq!(properties.to_string(), STOCK);
+
+        properties.set("gamemode", "creative");
+        properties.set(CONTENT_LOG_CONSOLE_OUTPUT_ENABLED, true);
+        assert_eq!(
+            properties.to_string(),
+            STOCK.replace("survival", "creative") + "content-log-console-output-enabled=true\n"
+        );
+        assert_eq!(
+            properties
+                .get_parsed::<bool>(CONTENT_LOG_CONSOLE_OUTPUT_ENABLED)
+                .unwrap(),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_round_trip_keeps_text_of_unchanged_lines() {
+        let content = "server-name = Dedicated Server \n  gamemode=survival\nmotd=\n";
+        let mut properties = ServerProperties::parse(content);
+        assert_eq!(properties.get("server-name"), Some("Dedicated Server"));
+        assert_eq!(properties.to_string(), content);
+
+        properties.set("gamemode", "creative");
+        assert_eq!(
+            properties.to_string(),
+            "server-name = Dedicated Server \ngamemode=creative\nmotd=\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_values_and_overrides_are_rejected() {
+        let properties = ServerProperties::parse("server-port=abc\n");
+        assert!(matches!(
+            properties.server_port(),
+            Err(PropertiesError::InvalidValue { .. })
+        ));
+        assert_eq!(ServerProperties::default().level_name(), DEFAULT_LEVEL_NAME);
+
+        assert_eq!(
+            parse_override("level-seed=123").unwrap(),
+            ("level-seed".to_string(), "123".to_string())
+        );
+        assert_eq!(
+            parse_override("motd=a=b").unwrap(),
+            ("motd".to_string(), "a=b".to_string())
+        );
+        assert!(parse_override("gamemode").is_err());
+        assert!(parse_override("=creative").is_err());
+    }
+
+    #[test]
+    fn test_write_replaces_linked_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = dir.path().join("shared.properties");
+        let path = dir.path().join(SERVER_PROPERTIES_FILE_NAME);
+        fs::write(&shared, STOCK).unwrap();
+        fs::hard_link(&shared, &path).unwrap();
+
+        let mut properties = ServerProperties::read(&path).unwrap();
+        properties.set(SERVER_PORT, 19200);
+        properties.write(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&shared).unwrap(), STOCK);
+        assert_eq!(
+            ServerProperties::read(&path)
+                .unwrap()
+                .server_port()
+                .unwrap(),
+            Some(19200)
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+static REPORTER: RwLock<Option<Arc<dyn Reporter>>> = RwLock::new(None);
+
+/// Something that happened while the library was working, for a [`Reporter`] to display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A step of a longer operation started, e.g. `Writing integrity manifest...`.
+    Status(String),
+    /// Something went wrong, but the operation carried on.
+    Warning(String),
+    /// The latest-version lookup made progress.
+    LookupProgress(String),
+    /// The primary latest-version lookup failed and the fallback is being tried.
+    LookupFallback(String),
+    /// A server zip download started.
+    DownloadStarted { version: String, url: String },
+    /// An interrupted download is being resumed from `offset` bytes.
+    DownloadResumed { offset: u64 },
+    /// More of the server zip was downloaded.
+    DownloadProgress { downloaded: u64, total: u64 },
+    /// A download attempt failed and will be retried after `delay`.
+    DownloadRetry {
+        error: String,
+        delay: Duration,
+        attempt: u32,
+        max_attempts: u32,
+    },
+    /// The server zip was downloaded completely.
+    DownloadComplete,
+    /// A server is being installed from a local zip.
+    InstallStarted { zip_path: PathBuf },
+    /// Extraction of the server zip started.
+    ExtractionStarted,
+    /// Another file of the server zip was extracted.
+    ExtractionProgress { extracted: usize, total: usize },
+    /// All files of the server zip were extracted.
+    ExtractionComplete,
+    /// The freshly extracted server reported its version.
+    VersionDetected(String),
+    /// Files of a new install were shared with other installed versions.
+    FilesShared { files: usize, bytes: u64 },
+    /// A server started by the validator moved to another phase.
+    ServerPhase(ServerPhase),
+    /// A raw line of server output, only reported when verbose output was requested.
+    ServerLog(String),
+    /// A log entry collected into the validation result.
+    LogEntry { level: LogLevel, line: String },
+}
+
+/// The phases a server goes through during validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServerPhase {
+    /// The packs are being linked into the server directory.
+    LinkingPacks,
+    Starting,
+    Started,
+    /// The packs are being loaded and their log entries collected.
+    Validating,
+    /// The server was quiet for this long, so validation is complete.
+    Idle(Duration),
+    Stopping,
+    Stopped,
+}
+
+/// The level of a server log entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warning,
+    Info,
+}
+
+/// Receives the events the library reports while it works.
+///
+/// Nothing is printed until a reporter is installed with [`set_reporter`].
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: &Event);
+}
+
+impl<F> Reporter for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn report(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Sets the reporter receiving events for the whole process.
+pub fn set_reporter(reporter: impl Reporter + 'static) {
+    *REPORTER.write().unwrap() = Some(Arc::new(reporter));
+}
+
+/// Sends an event to the installed reporter, if any.
+pub(crate) fn report(event: Event) {
+    // Clone the handle so a reporter can't deadlock by reporting or replacing itself
+    let reporter = REPORTER.read().unwrap().clone();
+    if let Some(reporter) = reporter {
+        reporter.report(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Uninstalls the global reporter when dropped, so it can't outlive the test installing it.
+    struct ResetReporter;
+
+    impl Drop for ResetReporter {
+        fn drop(&mut self) {
+            *REPORTER.write().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+
+    #[test]
+    fn test_report_reaches_installed_reporter() {
+        static RECEIVED: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+        report(Event::Status("before".to_string()));
+        let _reset = ResetReporter;
+        set_reporter(|event: &Event| RECEIVED.lock().unwrap().push(event.clone()));
+        report(Event::ServerPhase(ServerPhase::Started));
+
+        let received = RECEIVED.lock().unwrap();
+        assert!(received.contains(&Event::ServerPhase(ServerPhase::Started)));
+        assert!(!received.contains(&Event::Status("before".to_string())));
+    }
+}
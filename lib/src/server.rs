@@ -1,13 +1,125 @@
-use crate::server_path::get_server_path;
-use anyhow::Result;
+use crate::dedup::{DedupError, STORE_DIR_NAME, sweep_store};
+use crate::error::ErrorKind;
+use crate::instance::{InstanceError, ServerInstance};
+use crate::integrity::InstallManifest;
+use crate::server_path::{ServerPathError, get_server_path};
+use crate::snapshot::lock_file_path;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-/// Returns a list of downloaded server versions
-pub fn list_servers() -> Result<Vec<String>> {
-    let path = get_server_path(false)?;
+/// Version alias resolving to the newest preview build.
+pub const PREVIEW_ALIAS: &str = "preview";
 
-    let versions = std::fs::read_dir(path)?
+const PREVIEW_SUFFIX: &str = "-preview";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    #[error(transparent)]
+    ServerPath(#[from] ServerPathError),
+    #[error("Invalid server version: {0}")]
+    InvalidVersion(String),
+    #[error("Server version {0} is not installed")]
+    NotInstalled(String),
+    #[error("At least one version per channel must be kept")]
+    InvalidKeep,
+    #[error("Failed to read {}", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to remove {}", path.display())]
+    RemoveFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to move server into place at {}", path.display())]
+    InstallFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Dedup(#[from] DedupError),
+    #[error(transparent)]
+    Instance(#[from] InstanceError),
+    #[error("Server exited without reporting its version")]
+    VersionNotReported,
+    #[error("Server did not report its version within {0} seconds")]
+    VersionTimeout(u64),
+}
+
+impl ServerError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ServerError::ServerPath(e) => e.kind(),
+            ServerError::Instance(e) => e.kind(),
+            ServerError::InvalidVersion(_)
+            | ServerError::NotInstalled(_)
+            | ServerError::InvalidKeep => ErrorKind::Usage,
+            ServerError::ReadFailed { .. }
+            | ServerError::RemoveFailed { .. }
+            | ServerError::InstallFailed { .. }
+            | ServerError::Dedup(_)
+            | ServerError::VersionNotReported
+            | ServerError::VersionTimeout(_) => ErrorKind::Infrastructure,
+        }
+    }
+}
+
+/// The release channel a server build belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Regular releases, as shipped to players.
+    #[default]
+    Release,
+    /// Preview builds of upcoming releases.
+    Preview,
+}
+
+impl Channel {
+    /// Returns the name of the directory a version of this channel is installed in.
+    ///
+    /// Release builds are installed as `<version>`, preview builds as `<version>-preview`, so
+    /// both can be installed side by side.
+    pub fn dir_name(&self, version: &str) -> String {
+        match self {
+            Channel::Release => version.to_string(),
+            Channel::Preview => format!("{}{}", version, PREVIEW_SUFFIX),
+        }
+    }
+
+    /// Splits an install directory name into its channel and version.
+    pub fn from_dir_name(dir_name: &str) -> (Channel, &str) {
+        match dir_name.strip_suffix(PREVIEW_SUFFIX) {
+            Some(version) => (Channel::Preview, version),
+            None => (Channel::Release, dir_name),
+        }
+    }
+}
+
+/// Returns a list of downloaded server versions, oldest first
+///
+/// Release builds are listed before preview builds, which are named `<version>-preview`.
+/// Hidden directories, such as in-progress extractions, are not included.
+pub fn list_servers() -> Result<Vec<String>, ServerError> {
+    list_servers_in(&get_server_path(false)?)
+}
+
+fn list_servers_in(server_path: &Path) -> Result<Vec<String>, ServerError> {
+    let mut versions = std::fs::read_dir(server_path)
+        .map_err(|source| ServerError::ReadFailed {
+            path: server_path.to_path_buf(),
+            source,
+        })?
         .filter_map(|entry| entry.ok())
         .filter(|entry| entry.path().is_dir())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
         .map(|entry| {
             entry
                 .path()
@@ -19,5 +131,531 @@ pub fn list_servers() -> Result<Vec<String>> {
         })
         .collect::<Vec<String>>();
 
+    versions.sort_by(|a, b| {
+        let (a_channel, a_version) = Channel::from_dir_name(a);
+        let (b_channel, b_version) = Channel::from_dir_name(b);
+        (a_channel == Channel::Preview)
+            .cmp(&(b_channel == Channel::Preview))
+            .then_with(|| compare_versions(a_version, b_version))
+    });
+
     Ok(versions)
 }
+
+/// Returns the install directory name of the newest downloaded server in `channel`, if any.
+pub fn latest_installed(channel: Channel) -> Result<Option<String>, ServerError> {
+    latest_installed_in(&get_server_path(false)?, channel)
+}
+
+fn latest_installed_in(
+    server_path: &Path,
+    channel: Channel,
+) -> Result<Option<String>, ServerError> {
+    Ok(list_servers_in(server_path)?
+        .into_iter()
+        .rfind(|name| Channel::from_dir_name(name).0 == channel))
+}
+
+/// Resolves a user-supplied version to an install directory name.
+///
+/// `None` resolves to the newest installed release and [`PREVIEW_ALIAS`] to the newest installed
+/// preview. Anything else is returned unchanged.
+pub fn resolve_installed_version(version: Option<&str>) -> Result<Option<String>, ServerError> {
+    resolve_installed_version_in(&get_server_path(false)?, version)
+}
+
+pub(crate) fn resolve_installed_version_in(
+    server_path: &Path,
+    version: Option<&str>,
+) -> Result<Option<String>, ServerError> {
+    match version {
+        None => latest_installed_in(server_path, Channel::Release),
+        Some(PREVIEW_ALIAS) => latest_installed_in(server_path, Channel::Preview),
+        Some(version) => Ok(Some(version.to_string())),
+    }
+}
+
+/// Details about an installed server version.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    /// The install directory name, as accepted by `--version`.
+    pub name: String,
+    /// The server version, without any channel suffix.
+    pub version: String,
+    pub channel: Channel,
+    pub path: PathBuf,
+    /// Total size of the installed files in bytes.
+    pub size_bytes: u64,
+    /// Install time in seconds since the Unix epoch, if an integrity manifest was recorded.
+    pub installed_at: Option<u64>,
+    /// Where the server zip came from, if an integrity manifest was recorded.
+    pub source_url: Option<String>,
+}
+
+/// Returns details about an installed server version.
+///
+/// # Arguments
+///
+/// * `name` - The install directory name, e.g. `1.21.84.1` or `1.21.100.20-preview`
+///
+/// # Returns
+///
+/// * `Ok(ServerInfo)` - The server's details
+/// * `Err(ServerError)` - If the version is not installed or could not be read
+pub fn server_info(name: &str) -> Result<ServerInfo, ServerError> {
+    server_info_in(&get_server_path(false)?, name)
+}
+
+fn server_info_in(server_path: &Path, name: &str) -> Result<ServerInfo, ServerError> {
+    let path = installed_server_path(server_path, name)?;
+    let (channel, version) = Channel::from_dir_name(name);
+    let manifest = InstallManifest::read(&path).ok();
+
+    Ok(ServerInfo {
+        name: name.to_string(),
+        version: version.to_string(),
+        channel,
+        size_bytes: dir_size(&path)?,
+        installed_at: manifest.as_ref().map(|manifest| manifest.installed_at),
+        source_url: manifest.map(|manifest| manifest.source_url),
+        path,
+    })
+}
+
+/// Deletes an installed server version.
+///
+/// # Arguments
+///
+/// * `name` - The install directory name, e.g. `1.21.84.1` or `1.21.100.20-preview`
+pub fn remove_server(name: &str) -> Result<(), ServerError> {
+    remove_server_in(&get_server_path(false)?, name)
+}
+
+fn remove_server_in(server_path: &Path, name: &str) -> Result<(), ServerError> {
+    let path = installed_server_path(server_path, name)?;
+    std::fs::remove_dir_all(&path).map_err(|source| ServerError::RemoveFailed {
+        path: path.clone(),
+        source,
+    })?;
+
+    // The snapshot lock file lives next to the version, so it would otherwise be left behind
+    let lock_path = lock_file_path(&path);
+    if let Err(source) = std::fs::remove_file(&lock_path)
+        && source.kind() != std::io::ErrorKind::NotFound
+    {
+        return Err(ServerError::RemoveFailed {
+            path: lock_path,
+            source,
+        });
+    }
+
+    // Drop the shared copies of files only this version used
+    sweep_store(&server_path.join(STORE_DIR_NAME))?;
+    Ok(())
+}
+
+/// Deletes all but the newest `keep` installed versions of each channel.
+///
+/// Releases and previews are pruned separately, so keeping 2 keeps the two newest releases and
+/// the two newest previews.
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` - The install directory names that were removed
+/// * `Err(ServerError)` - If `keep` is 0, or the servers could not be listed or removed
+pub fn prune_servers(keep: usize) -> Result<Vec<String>, ServerError> {
+    prune_servers_in(&get_server_path(false)?, keep)
+}
+
+fn prune_servers_in(server_path: &Path, keep: usize) -> Result<Vec<String>, ServerError> {
+    let removed = servers_to_prune_in(server_path, keep)?;
+    for name in &removed {
+        remove_server_in(server_path, name)?;
+    }
+
+    Ok(removed)
+}
+
+/// Lists the installed versions [`prune_servers`] would delete, without deleting anything.
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` - The install directory names that would be removed
+/// * `Err(ServerError)` - If `keep` is 0, or the servers could not be listed
+pub fn servers_to_prune(keep: usize) -> Result<Vec<String>, ServerError> {
+    servers_to_prune_in(&get_server_path(false)?, keep)
+}
+
+fn servers_to_prune_in(server_path: &Path, keep: usize) -> Result<Vec<String>, ServerError> {
+    if keep == 0 {
+        return Err(ServerError::InvalidKeep);
+    }
+
+    let installed = list_servers_in(server_path)?;
+    let mut excess_versions = Vec::new();
+
+    for channel in [Channel::Release, Channel::Preview] {
+        let versions: Vec<&String> = installed
+            .iter()
+            .filter(|name| Channel::from_dir_name(name).0 == channel)
+            .collect();
+        let excess = versions.len().saturating_sub(keep);
+        excess_versions.extend(versions[..excess].iter().map(|name| name.to_string()));
+    }
+
+    Ok(excess_versions)
+}
+
+/// Moves a complete server install at `staged_path` into place at `version_path`.
+///
+/// Any existing install is first renamed aside and only removed once the new one is in place,
+/// so `version_path` always holds either the old or the new complete install. `staged_path` is
+/// removed if it can't be moved.
+#[cfg(any(feature = "download", feature = "cache"))]
+pub(crate) fn install_staged(staged_path: &Path, version_path: &Path) -> Result<(), ServerError> {
+    let install_failed = |source| ServerError::InstallFailed {
+        path: version_path.to_path_buf(),
+        source,
+    };
+
+    let previous = if version_path.exists() {
+        let aside = sibling_path(version_path, ".old");
+        std::fs::rename(version_path, &aside).map_err(install_failed)?;
+        Some(aside)
+    } else {
+        None
+    };
+
+    if let Err(e) = std::fs::rename(staged_path, version_path) {
+        std::fs::remove_dir_all(staged_path).ok();
+        if let Some(previous) = &previous {
+            std::fs::rename(previous, version_path).ok();
+        }
+        return Err(install_failed(e));
+    }
+
+    if let Some(previous) = previous {
+        std::fs::remove_dir_all(previous).ok();
+    }
+
+    Ok(())
+}
+
+/// Returns a hidden path next to `path`, e.g. `server/.1.21.84.1.old`.
+pub(crate) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}{}", name, suffix))
+}
+
+/// Returns the path of an installed server version, failing if it doesn't exist.
+fn installed_server_path(server_path: &Path, name: &str) -> Result<PathBuf, ServerError> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        return Err(ServerError::InvalidVersion(name.to_string()));
+    }
+
+    let path = server_path.join(name);
+    if !path.is_dir() {
+        return Err(ServerError::NotInstalled(name.to_string()));
+    }
+
+    Ok(path)
+}
+
+/// Returns the total size of the regular files under `dir`, without following symlinks.
+fn dir_size(dir: &Path) -> Result<u64, ServerError> {
+    let read_failed = |source| ServerError::ReadFailed {
+        path: dir.to_path_buf(),
+        source,
+    };
+
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir).map_err(read_failed)? {
+        let entry = entry.map_err(read_failed)?;
+        let file_type = entry.file_type().map_err(read_failed)?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata().map_err(read_failed)?.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Compares two dotted version strings numerically, component by component.
+///
+/// Non-numeric components are compared as text, so `1.21.100.1` sorts after `1.21.9.1`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_part), Some(b_part)) => {
+                let ordering = match (a_part.parse::<u64>(), b_part.parse::<u64>()) {
+                    (Ok(a_num), Ok(b_num)) => a_num.cmp(&b_num),
+                    _ => a_part.cmp(b_part),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// Launches the server in `server_path` just long enough to read the version it reports.
+///
+/// The server prints a line such as `[2025-06-17 10:00:00:000 INFO] Version: 1.21.84.1` early
+/// during startup. The process is killed as soon as that line is seen, or once `timeout` elapses.
+///
+/// # Arguments
+///
+/// * `server_path` - Path to the server directory containing bedrock_server
+/// * `timeout` - How long to wait for the version line
+///
+/// # Returns
+///
+/// * `Ok(String)` - The version reported by the server
+/// * `Err(ServerError)` - If the server could not be started or didn't report a version in time
+pub async fn detect_server_version(
+    server_path: &Path,
+    timeout: Duration,
+) -> Result<String, ServerError> {
+    let mut server = ServerInstance::start(server_path).await?;
+
+    let version = tokio::time::timeout(timeout, async {
+        while let Some(line) = server.next_line().await {
+            if let Some(version) = parse_version_line(&line) {
+                return Some(version);
+            }
+        }
+        None
+    })
+    .await;
+
+    server.kill().await.ok();
+
+    match version {
+        Ok(Some(version)) => Ok(version),
+        Ok(None) => Err(ServerError::VersionNotReported),
+        Err(_) => Err(ServerError::VersionTimeout(timeout.as_secs())),
+    }
+}
+
+/// Extracts the version from a server log line containing `Version: <version>`.
+pub(crate) fn parse_version_line(line: &str) -> Option<String> {
+    let (_, rest) = line.split_once("Version: ")?;
+    let version: String = rest
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let version = version.trim_end_matches('.');
+
+    if version.contains('.') {
+        Some(version.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrity::MANIFEST_FILE_NAME;
+    use crate::snapshot::SnapshotLock;
+    use crate::test_support::{FakeServer, install_fixture};
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.21.84.1", "1.21.84.1"), Ordering::Equal);
+        assert_eq!(compare_versions("1.21.9.1", "1.21.100.1"), Ordering::Less);
+        assert_eq!(
+            compare_versions("1.21.100.1", "1.21.84.1"),
+            Ordering::Greater
+        );
+        assert_eq!(compare_versions("1.21.84", "1.21.84.1"), Ordering::Less);
+    }
+
+    fn install_fixtures(names: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for name in names {
+            install_fixture(dir.path(), name, "00");
+        }
+        dir
+    }
+
+    #[test]
+    fn test_list_servers_sorted_by_version() {
+        let dir = install_fixtures(&[
+            "1.21.100.1",
+            "1.21.9.1",
+            "1.21.110.20-preview",
+            "1.21.84.1",
+            ".1.21.90.1.extract-abc",
+        ]);
+
+        assert_eq!(
+            list_servers_in(dir.path()).unwrap(),
+            vec!["1.21.9.1", "1.21.84.1", "1.21.100.1", "1.21.110.20-preview"]
+        );
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_per_channel() {
+        let dir = install_fixtures(&[
+            "1.21.9.1",
+            "1.21.84.1",
+            "1.21.100.1",
+            "1.21.100.20-preview",
+            "1.21.110.20-preview",
+        ]);
+
+        let removed = prune_servers_in(dir.path(), 1).unwrap();
+
+        assert_eq!(
+            removed,
+            vec!["1.21.9.1", "1.21.84.1", "1.21.100.20-preview"]
+        );
+        assert_eq!(
+            list_servers_in(dir.path()).unwrap(),
+            vec!["1.21.100.1", "1.21.110.20-preview"]
+        );
+    }
+
+    #[test]
+    fn test_prune_rejects_keeping_nothing() {
+        let dir = install_fixtures(&["1.21.84.1"]);
+
+        assert!(matches!(
+            prune_servers_in(dir.path(), 0),
+            Err(ServerError::InvalidKeep)
+        ));
+        assert_eq!(list_servers_in(dir.path()).unwrap(), vec!["1.21.84.1"]);
+    }
+
+    #[cfg(any(feature = "download", feature = "cache"))]
+    #[test]
+    fn test_install_staged_replaces_existing_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let version_path = dir.path().join("1.21.84.1");
+        std::fs::create_dir_all(&version_path).unwrap();
+        std::fs::write(version_path.join("old.txt"), b"old").unwrap();
+
+        let staged_path = dir.path().join(".1.21.84.1.staged");
+        std::fs::create_dir_all(&staged_path).unwrap();
+        std::fs::write(staged_path.join("new.txt"), b"new").unwrap();
+        install_staged(&staged_path, &version_path).unwrap();
+
+        assert!(version_path.join("new.txt").exists());
+        assert!(!version_path.join("old.txt").exists());
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .collect();
+        assert_eq!(leftovers, vec![std::ffi::OsString::from("1.21.84.1")]);
+    }
+
+    #[test]
+    fn test_server_info() {
+        let dir = install_fixtures(&["1.21.100.20-preview"]);
+
+        let info = server_info_in(dir.path(), "1.21.100.20-preview").unwrap();
+
+        assert_eq!(info.version, "1.21.100.20");
+        assert_eq!(info.channel, Channel::Preview);
+        let manifest_len = std::fs::metadata(info.path.join(MANIFEST_FILE_NAME))
+            .unwrap()
+            .len();
+        assert_eq!(info.size_bytes, 6 + 2 + manifest_len);
+        assert!(info.installed_at.is_some());
+        assert_eq!(
+            info.source_url.as_deref(),
+            Some("https://example.com/bedrock-server-1.21.100.20.zip")
+        );
+    }
+
+    #[test]
+    fn test_remove_rejects_missing_and_unsafe_names() {
+        let dir = install_fixtures(&["1.21.84.1"]);
+
+        assert!(remove_server_in(dir.path(), "1.21.9.1").is_err());
+        assert!(remove_server_in(dir.path(), "../1.21.84.1").is_err());
+        remove_server_in(dir.path(), "1.21.84.1").unwrap();
+        assert!(!dir.path().join("1.21.84.1").exists());
+    }
+
+    #[test]
+    fn test_remove_and_prune_delete_lock_files() {
+        let dir = install_fixtures(&["1.21.9.1", "1.21.84.1", "1.21.100.1"]);
+        for name in ["1.21.9.1", "1.21.84.1", "1.21.100.1"] {
+            drop(SnapshotLock::acquire(&dir.path().join(name)).unwrap());
+        }
+
+        prune_servers_in(dir.path(), 2).unwrap();
+        remove_server_in(dir.path(), "1.21.84.1").unwrap();
+
+        let lock_files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".lock"))
+            .collect();
+        assert_eq!(lock_files, vec![".1.21.100.1.lock"]);
+    }
+
+    #[test]
+    fn test_channel_dir_names() {
+        assert_eq!(Channel::Release.dir_name("1.21.84.1"), "1.21.84.1");
+        assert_eq!(
+            Channel::Preview.dir_name("1.21.100.20"),
+            "1.21.100.20-preview"
+        );
+        assert_eq!(
+            Channel::from_dir_name("1.21.100.20-preview"),
+            (Channel::Preview, "1.21.100.20")
+        );
+        assert_eq!(
+            Channel::from_dir_name("1.21.84.1"),
+            (Channel::Release, "1.21.84.1")
+        );
+    }
+
+    #[test]
+    fn test_parse_version_line() {
+        assert_eq!(
+            parse_version_line("[2025-06-17 10:00:00:000 INFO] Version: 1.21.84.1"),
+            Some("1.21.84.1".to_string())
+        );
+        assert_eq!(
+            parse_version_line("NO LOG FILE! - [2025-06-17 10:00:00:000 INFO] Version: 1.21.90.3 "),
+            Some("1.21.90.3".to_string())
+        );
+        assert_eq!(
+            parse_version_line("[2025-06-17 10:00:00:000 INFO] Starting Server"),
+            None
+        );
+        assert_eq!(parse_version_line("Version: unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn test_detect_server_version_from_fake_server() {
+        let dir = tempfile::tempdir().unwrap();
+        FakeServer::new()
+            .log("INFO", "Starting Server")
+            .log("INFO", "Version: 1.21.84.1")
+            .run("sleep 30")
+            .write(dir.path());
+
+        let version = detect_server_version(dir.path(), Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(version, "1.21.84.1");
+    }
+}
@@ -1,28 +1,53 @@
+use crate::error::ErrorKind;
 use dirs::home_dir;
 use std::path::PathBuf;
 
+#[derive(Debug, thiserror::Error)]
+pub enum ServerPathError {
+    #[error("Could not find home directory, and no BEDROCK_SERVER_PATH environment variable set")]
+    HomeNotFound,
+    #[error(
+        "Server directory does not exist at {}. Please run 'bedrockci download' first.",
+        .0.display()
+    )]
+    Missing(PathBuf),
+    #[error("Failed to create server directory {}", path.display())]
+    CreateFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl ServerPathError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ServerPathError::HomeNotFound | ServerPathError::Missing(_) => ErrorKind::Usage,
+            ServerPathError::CreateFailed { .. } => ErrorKind::Infrastructure,
+        }
+    }
+}
+
 /// Gets the server path, either the ENV variable BEDROCK_SERVER_PATH or the default path
-pub fn get_server_path(create_if_missing: bool) -> Result<PathBuf, anyhow::Error> {
+pub fn get_server_path(create_if_missing: bool) -> Result<PathBuf, ServerPathError> {
     let server_path = match std::env::var("BEDROCK_SERVER_PATH") {
         Ok(path) => PathBuf::from(path),
-        Err(_) => {
-            let home = home_dir().ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Could not find home directory, and no BEDROCK_SERVER_PATH environment variable set"
-                )
-            })?;
-            home.join(".bedrockci/server")
-        }
+        Err(_) => home_dir()
+            .ok_or(ServerPathError::HomeNotFound)?
+            .join(".bedrockci/server"),
     };
 
     if !server_path.exists() {
         if create_if_missing {
-            std::fs::create_dir_all(&server_path)?;
+            std::fs::create_dir_all(&server_path).map_err(|source| {
+                ServerPathError::CreateFailed {
+                    path: server_path.clone(),
+                    source,
+                }
+            })?;
         } else {
-            return Err(anyhow::anyhow!(
-                "Server directory does not exist at {}. Please run 'bedrockci download' first.",
-                server_path.display()
-            ));
+            return Err(ServerPathError::Missing(server_path));
         }
     }
 
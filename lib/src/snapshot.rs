@@ -0,0 +1,380 @@
+use crate::error::ErrorKind;
+use crate::server::sibling_path;
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// Name of the directory a [`ServerSnapshot`] keeps the original files in, inside the server
+/// directory.
+pub const SNAPSHOT_DIR_NAME: &str = ".bedrockci-snapshot";
+
+/// Lists the snapshotted paths, so a snapshot left behind by a killed run can be restored.
+const PATHS_FILE_NAME: &str = ".paths";
+
+/// Written once every path was moved aside. Until then, a path missing from the snapshot may
+/// still be the original rather than something the run created.
+const COMPLETE_FILE_NAME: &str = ".complete";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("Failed to snapshot {}", path.display())]
+    CaptureFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to restore {}", path.display())]
+    RestoreFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to lock {}", path.display())]
+    LockFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Server {} is in use by another run", path.display())]
+    InUse { path: PathBuf },
+}
+
+impl SnapshotError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Infrastructure
+    }
+}
+
+/// Returns the path of the lock file [`SnapshotLock`] takes for a server directory.
+pub(crate) fn lock_file_path(server_path: &Path) -> PathBuf {
+    sibling_path(server_path, ".lock")
+}
+
+/// Exclusive hold on a server directory, so only one run at a time snapshots and restores it.
+///
+/// This is an advisory lock on a `.<version>.lock` file next to the server directory. The OS
+/// releases it when the holder exits, even if it is killed, so a snapshot found while holding
+/// the lock was left behind by a run that is gone.
+#[derive(Debug)]
+pub struct SnapshotLock {
+    server_path: PathBuf,
+    _file: File,
+}
+
+impl SnapshotLock {
+    /// Locks the server directory and restores a snapshot left behind by a run that was killed.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_path` - Path to the server directory
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SnapshotLock)` - The lock, released when dropped
+    /// * `Err(SnapshotError)` - If another run holds the lock, or the leftover snapshot couldn't
+    ///   be restored
+    pub fn acquire(server_path: &Path) -> Result<Self, SnapshotError> {
+        let lock = Self::acquire_without_recovery(server_path)?;
+        restore_leftover(server_path)?;
+        Ok(lock)
+    }
+
+    fn acquire_without_recovery(server_path: &Path) -> Result<Self, SnapshotError> {
+        let lock_path = lock_file_path(server_path);
+        let lock_failed = |source| SnapshotError::LockFailed {
+            path: lock_path.clone(),
+            source,
+        };
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(lock_failed)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self {
+                server_path: server_path.to_path_buf(),
+                _file: file,
+            }),
+            Err(TryLockError::WouldBlock) => Err(SnapshotError::InUse {
+                path: server_path.to_path_buf(),
+            }),
+            Err(TryLockError::Error(source)) => Err(lock_failed(source)),
+        }
+    }
+
+    /// Moves `paths` out of the server directory into a snapshot, which keeps holding the lock.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Paths relative to the server directory, e.g. `worlds/Bedrock level`. They
+    ///   don't have to exist
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ServerSnapshot)` - The snapshot, restored when dropped
+    /// * `Err(SnapshotError)` - If a path couldn't be moved aside. Anything already moved is put
+    ///   back
+    pub fn capture(self, paths: &[&Path]) -> Result<ServerSnapshot, SnapshotError> {
+        let server_path = self.server_path.clone();
+        let snapshot_path = server_path.join(SNAPSHOT_DIR_NAME);
+        let capture_failed = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| SnapshotError::CaptureFailed { path, source }
+        };
+        fs::create_dir_all(&snapshot_path).map_err(capture_failed(&snapshot_path))?;
+
+        let snapshot = ServerSnapshot {
+            server_path: server_path.clone(),
+            paths: paths.iter().map(|path| path.to_path_buf()).collect(),
+            restored: false,
+            _lock: self,
+        };
+        let listing: String = paths
+            .iter()
+            .map(|path| format!("{}\n", path.display()))
+            .collect();
+        let paths_file = snapshot_path.join(PATHS_FILE_NAME);
+        fs::write(&paths_file, listing).map_err(capture_failed(&paths_file))?;
+
+        for path in paths {
+            let original = server_path.join(path);
+            if fs::symlink_metadata(&original).is_err() {
+                continue;
+            }
+            let saved = snapshot_path.join(path);
+            if let Some(parent) = saved.parent() {
+                fs::create_dir_all(parent).map_err(capture_failed(parent))?;
+            }
+            fs::rename(&original, &saved).map_err(capture_failed(&original))?;
+        }
+
+        let complete_file = snapshot_path.join(COMPLETE_FILE_NAME);
+        fs::write(&complete_file, "").map_err(capture_failed(&complete_file))?;
+
+        Ok(snapshot)
+    }
+}
+
+/// Files and directories of an installed server, moved aside so a run starts from a fresh state.
+///
+/// Anything the run creates at the snapshotted paths is removed and the originals are put back
+/// by [`ServerSnapshot::restore`], or when the snapshot is dropped, so a failed or cancelled run
+/// leaves the server as it found it. The server directory stays locked until then, see
+/// [`SnapshotLock`].
+#[derive(Debug)]
+pub struct ServerSnapshot {
+    server_path: PathBuf,
+    paths: Vec<PathBuf>,
+    restored: bool,
+    // Fields are dropped after `drop` ran, so the lock is held until the paths are restored
+    _lock: SnapshotLock,
+}
+
+impl ServerSnapshot {
+    /// Locks the server directory and moves `paths` out of it into a snapshot.
+    ///
+    /// Shorthand for [`SnapshotLock::acquire`] followed by [`SnapshotLock::capture`]. Use those
+    /// to read the server's files after a leftover snapshot was restored, but before new paths
+    /// are moved aside.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_path` - Path to the server directory
+    /// * `paths` - Paths relative to `server_path`, e.g. `worlds/Bedrock level`. They don't have
+    ///   to exist
+    pub fn capture(server_path: &Path, paths: &[&Path]) -> Result<Self, SnapshotError> {
+        SnapshotLock::acquire(server_path)?.capture(paths)
+    }
+
+    /// Returns where the original of `path` is kept while the snapshot is held, if it existed.
+    pub fn saved_path(&self, path: &Path) -> Option<PathBuf> {
+        let saved = self.server_path.join(SNAPSHOT_DIR_NAME).join(path);
+        fs::symlink_metadata(&saved).is_ok().then_some(saved)
+    }
+
+    /// Removes anything the run created at the snapshotted paths and puts the originals back.
+    pub fn restore(mut self) -> Result<(), SnapshotError> {
+        self.restored = true;
+        restore_paths(&self.server_path, &self.paths)
+    }
+}
+
+impl Drop for ServerSnapshot {
+    fn drop(&mut self) {
+        if !self.restored {
+            restore_paths(&self.server_path, &self.paths).ok();
+        }
+    }
+}
+
+/// Restores a snapshot left behind by a run that was killed before it could restore it.
+///
+/// A snapshot held by a run that is still going is left alone.
+///
+/// # Arguments
+///
+/// * `server_path` - Path to the server directory
+///
+/// # Returns
+///
+/// * `Ok(bool)` - Whether a snapshot was found and restored
+/// * `Err(SnapshotError)` - If another run holds the server directory, or the snapshot couldn't
+///   be restored
+pub fn recover_snapshot(server_path: &Path) -> Result<bool, SnapshotError> {
+    let _lock = SnapshotLock::acquire_without_recovery(server_path)?;
+    restore_leftover(server_path)
+}
+
+/// Restores a leftover snapshot. Only called while holding the [`SnapshotLock`].
+fn restore_leftover(server_path: &Path) -> Result<bool, SnapshotError> {
+    let snapshot_path = server_path.join(SNAPSHOT_DIR_NAME);
+    if !snapshot_path.is_dir() {
+        return Ok(false);
+    }
+
+    let paths: Vec<PathBuf> = fs::read_to_string(snapshot_path.join(PATHS_FILE_NAME))
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    restore_paths(server_path, &paths)?;
+    Ok(true)
+}
+
+fn restore_paths(server_path: &Path, paths: &[PathBuf]) -> Result<(), SnapshotError> {
+    let snapshot_path = server_path.join(SNAPSHOT_DIR_NAME);
+    let complete = snapshot_path.join(COMPLETE_FILE_NAME).exists();
+    let restore_failed = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| SnapshotError::RestoreFailed { path, source }
+    };
+
+    for path in paths {
+        let target = server_path.join(path);
+        let saved = snapshot_path.join(path);
+        let has_saved = fs::symlink_metadata(&saved).is_ok();
+        if !has_saved && !complete {
+            // The capture never got to this path, so it's still the original
+            continue;
+        }
+
+        remove_path(&target).map_err(restore_failed(&target))?;
+        if has_saved {
+            fs::rename(&saved, &target).map_err(restore_failed(&target))?;
+        }
+    }
+
+    fs::remove_dir_all(&snapshot_path).map_err(restore_failed(&snapshot_path))
+}
+
+/// Removes a file, directory or symlink, without following symlinks.
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORLD: &str = "worlds/Bedrock level";
+    const LINK: &str = "behavior_packs/TESTING_PACK_BP";
+
+    fn server_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(WORLD)).unwrap();
+        fs::write(dir.path().join(WORLD).join("level.dat"), "original").unwrap();
+        fs::create_dir_all(dir.path().join("behavior_packs")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_snapshot_resets_and_restores_paths() {
+        let dir = server_dir();
+        let paths = [Path::new(WORLD), Path::new(LINK)];
+
+        let snapshot = ServerSnapshot::capture(dir.path(), &paths).unwrap();
+        assert!(!dir.path().join(WORLD).exists());
+        assert_eq!(
+            fs::read_to_string(
+                snapshot
+                    .saved_path(Path::new(WORLD))
+                    .unwrap()
+                    .join("level.dat")
+            )
+            .unwrap(),
+            "original"
+        );
+        assert_eq!(snapshot.saved_path(Path::new(LINK)), None);
+
+        fs::create_dir_all(dir.path().join(WORLD)).unwrap();
+        fs::write(dir.path().join(WORLD).join("level.dat"), "changed").unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join(LINK)).unwrap();
+        snapshot.restore().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join(WORLD).join("level.dat")).unwrap(),
+            "original"
+        );
+        assert!(fs::symlink_metadata(dir.path().join(LINK)).is_err());
+        assert!(!dir.path().join(SNAPSHOT_DIR_NAME).exists());
+    }
+
+    #[test]
+    fn test_dropped_snapshot_restores_paths() {
+        let dir = server_dir();
+
+        let snapshot = ServerSnapshot::capture(dir.path(), &[Path::new(WORLD)]).unwrap();
+        fs::create_dir_all(dir.path().join(WORLD)).unwrap();
+        drop(snapshot);
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join(WORLD).join("level.dat")).unwrap(),
+            "original"
+        );
+    }
+
+    #[test]
+    fn test_stale_snapshot_is_recovered() {
+        let dir = server_dir();
+
+        let mut snapshot = ServerSnapshot::capture(dir.path(), &[Path::new(WORLD)]).unwrap();
+        // A killed run never gets to restore its snapshot, but its lock is released
+        snapshot.restored = true;
+        drop(snapshot);
+        fs::create_dir_all(dir.path().join(WORLD)).unwrap();
+
+        assert!(recover_snapshot(dir.path()).unwrap());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(WORLD).join("level.dat")).unwrap(),
+            "original"
+        );
+        assert!(!recover_snapshot(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_snapshot_of_running_run_is_not_recovered() {
+        let dir = server_dir();
+
+        let snapshot = ServerSnapshot::capture(dir.path(), &[Path::new(WORLD)]).unwrap();
+        assert!(matches!(
+            recover_snapshot(dir.path()),
+            Err(SnapshotError::InUse { .. })
+        ));
+        assert!(matches!(
+            ServerSnapshot::capture(dir.path(), &[Path::new(WORLD)]),
+            Err(SnapshotError::InUse { .. })
+        ));
+        assert!(snapshot.saved_path(Path::new(WORLD)).is_some());
+        assert!(!dir.path().join(WORLD).exists());
+
+        snapshot.restore().unwrap();
+        assert!(!recover_snapshot(dir.path()).unwrap());
+    }
+}
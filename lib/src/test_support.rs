@@ -0,0 +1,116 @@
+//! Test fixtures shared by the unit tests of several modules.
+
+use crate::integrity::InstallManifest;
+use crate::server::Channel;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Contents of the fixture's `bedrock_server` binary.
+pub const FIXTURE_BINARY: &[u8] = b"binary";
+
+/// Writes a minimal installed server with an install manifest.
+///
+/// # Arguments
+///
+/// * `server_path` - The directory holding the installed versions
+/// * `name` - The install directory name, e.g. `1.21.84.1` or `1.21.100.20-preview`
+/// * `zip_sha256` - The archive checksum recorded in the manifest
+///
+/// # Returns
+///
+/// The path of the installed version
+pub fn install_fixture(server_path: &Path, name: &str, zip_sha256: &str) -> PathBuf {
+    let version_path = server_path.join(name);
+    let version = Channel::from_dir_name(name).1;
+    fs::create_dir_all(version_path.join("definitions")).unwrap();
+    fs::write(version_path.join("bedrock_server"), FIXTURE_BINARY).unwrap();
+    fs::set_permissions(
+        version_path.join("bedrock_server"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    fs::write(version_path.join("definitions/biomes.json"), b"{}").unwrap();
+    InstallManifest::generate(
+        &version_path,
+        version,
+        &format!("https://example.com/bedrock-server-{}.zip", version),
+        zip_sha256,
+    )
+    .unwrap()
+    .write(&version_path)
+    .unwrap();
+    version_path
+}
+
+/// Builds a shell script standing in for `bedrock_server`.
+///
+/// Log lines use the real server's format, so they are parsed like its output.
+pub struct FakeServer {
+    script: String,
+}
+
+impl FakeServer {
+    /// Starts an empty script.
+    pub fn new() -> Self {
+        Self {
+            script: "#!/bin/sh\n".to_string(),
+        }
+    }
+
+    /// Appends the usual startup of a server reporting version `1.21.84.1`.
+    pub fn started(self) -> Self {
+        self.log("INFO", "Version: 1.21.84.1")
+            .log("INFO", "Server started.")
+    }
+
+    /// Appends a raw shell command.
+    pub fn run(mut self, command: &str) -> Self {
+        self.script.push_str(command);
+        self.script.push('\n');
+        self
+    }
+
+    /// Appends a log line printed to stdout.
+    pub fn log(self, level: &str, message: &str) -> Self {
+        let line = format!("echo '[2025-06-17 10:00:00:000 {}] {}'", level, message);
+        self.run(&line)
+    }
+
+    /// Appends a log line printed to stderr.
+    pub fn log_stderr(self, level: &str, message: &str) -> Self {
+        let line = format!("echo '[2025-06-17 10:00:00:000 {}] {}' >&2", level, message);
+        self.run(&line)
+    }
+
+    /// Echoes every console command and exits cleanly on `stop`, like the real server.
+    pub fn console(self) -> Self {
+        self.run(
+            "while read -r line; do
+  echo \"[2025-06-17 10:00:00:000 INFO] Received: $line\"
+  if [ \"$line\" = stop ]; then
+    echo '[2025-06-17 10:00:00:000 INFO] Quit correctly'
+    exit 0
+  fi
+done",
+        )
+    }
+
+    /// Returns the script.
+    pub fn script(self) -> String {
+        self.script
+    }
+
+    /// Writes the script as an executable `bedrock_server` in `dir`.
+    ///
+    /// # Returns
+    ///
+    /// The path of the written executable
+    pub fn write(self, dir: &Path) -> PathBuf {
+        let exe = dir.join("bedrock_server");
+        fs::create_dir_all(dir).unwrap();
+        fs::write(&exe, self.script()).unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+        exe
+    }
+}
@@ -1,27 +1,108 @@
-use anyhow::Result;
-use colored::*;
+use crate::content_log::{CONTENT_LOG_DIR, ContentLogEntry, ContentLogError, ContentLogTail};
+use crate::error::ErrorKind;
+use crate::instance::{InstanceError, ServerInstance};
+use crate::properties::{
+    CONTENT_LOG_CONSOLE_OUTPUT_ENABLED, CONTENT_LOG_FILE_ENABLED, LEVEL_NAME, PropertiesError,
+    SERVER_PROPERTIES_FILE_NAME, ServerProperties, read_server_properties,
+};
+use crate::report::{Event, LogLevel, ServerPhase, report};
+use crate::server::{ServerError, parse_version_line, resolve_installed_version_in};
+use crate::server_path::{ServerPathError, get_server_path};
+use crate::snapshot::{SnapshotError, SnapshotLock};
+use crate::world::{WorldError, install_world};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use std::process::Command;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::time::{Duration, Instant};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command as TokioCommand;
 use tokio::select;
-use tokio::time::sleep;
+use tokio::time::{Instant as TokioInstant, interval, sleep_until};
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ValidationError {
-    #[error("Failed to copy pack: {0}")]
-    PackCopyFailed(String),
+    #[error("Failed to set up test pack files at {}", path.display())]
+    PackCopyFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid pack configuration {}", path.display())]
+    PackConfigInvalid {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
     #[error("Invalid pack path: {0}")]
     InvalidPackPath(String),
     #[error("Invalid server path: {0}")]
     InvalidServerPath(String),
-    #[error("Failed to start server: {0}")]
-    ServerStartFailed(String),
+    #[error("Server version {0} is not installed")]
+    ServerNotInstalled(String),
     #[error("Validation failed: {0}")]
     ValidationFailed(String),
+    #[error("Server exited before loading the packs")]
+    ServerExited {
+        /// The entries logged before the server exited.
+        result: Box<ValidationResult>,
+    },
+    #[error("Server did not load the packs within {seconds} seconds")]
+    StartupTimeout {
+        seconds: u64,
+        /// The entries logged before the timeout.
+        result: Box<ValidationResult>,
+    },
+    #[error("Validation cancelled")]
+    Cancelled,
+    #[error(transparent)]
+    ServerPath(#[from] ServerPathError),
+    #[error(transparent)]
+    Server(#[from] ServerError),
+    #[error(transparent)]
+    Instance(#[from] InstanceError),
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+    #[error(transparent)]
+    World(#[from] WorldError),
+    #[error(transparent)]
+    Properties(#[from] PropertiesError),
+    #[error(transparent)]
+    ContentLog(#[from] ContentLogError),
+}
+
+impl ValidationError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ValidationError::InvalidPackPath(_)
+            | ValidationError::InvalidServerPath(_)
+            | ValidationError::ServerNotInstalled(_)
+            | ValidationError::PackConfigInvalid { .. } => ErrorKind::Usage,
+            // The packs never got the chance to be validated, so these say nothing about them
+            ValidationError::PackCopyFailed { .. }
+            | ValidationError::ServerExited { .. }
+            | ValidationError::StartupTimeout { .. } => ErrorKind::Infrastructure,
+            ValidationError::ValidationFailed(_) => ErrorKind::Validation,
+            ValidationError::Cancelled => ErrorKind::Cancelled,
+            ValidationError::ServerPath(e) => e.kind(),
+            ValidationError::Server(e) => e.kind(),
+            ValidationError::Instance(e) => e.kind(),
+            ValidationError::Snapshot(e) => e.kind(),
+            ValidationError::World(e) => e.kind(),
+            ValidationError::Properties(e) => e.kind(),
+            ValidationError::ContentLog(e) => e.kind(),
+        }
+    }
+
+    /// Returns the entries the server logged before failing to load the packs, if any.
+    pub fn partial_result(&self) -> Option<&ValidationResult> {
+        match self {
+            ValidationError::ServerExited { result }
+            | ValidationError::StartupTimeout { result, .. } => Some(result),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -29,11 +110,40 @@ pub struct ValidationResult {
     pub errors: Vec<String>,
     pub warnings: Vec<String>,
     pub info: Vec<String>,
+    /// The server build as reported in the server's own log.
+    pub server_version: Option<String>,
+    /// Everything written to the content log file during the run, if it was enabled. Its entries
+    /// are merged into the ones above, without those also printed to the console.
+    pub content_log: Option<String>,
+}
+
+/// How long validation waits for the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// How long the server must stay quiet after loading the packs before validation completes.
+    pub last_log: Duration,
+    /// How long the server may take to start and load the packs. Waits indefinitely if `None`.
+    pub startup: Option<Duration>,
 }
 
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            last_log: Duration::from_secs(2),
+            startup: None,
+        }
+    }
+}
+
+/// How often the content log file is read while the server runs.
+const CONTENT_LOG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 const TESTING_BP_NAME: &str = "TESTING_PACK_BP";
 const TESTING_RP_NAME: &str = "TESTING_PACK_RP";
 
+/// The level name a template world is installed as.
+const TEMPLATE_LEVEL_NAME: &str = "BedrockCI template";
+
 #[derive(Debug, Deserialize)]
 struct Manifest {
     header: Header,
@@ -69,72 +179,88 @@ fn read_pack_manifest(pack_path: &Path) -> Result<Header, ValidationError> {
         ));
     }
 
-    let manifest_content = fs::read_to_string(manifest_path).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to read manifest.json: {}", e))
-    })?;
+    let manifest_content = fs::read_to_string(&manifest_path)
+        .map_err(|source| pack_copy_failed(&manifest_path, source))?;
 
-    let manifest: Manifest = serde_json::from_str(&manifest_content).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to parse manifest.json: {}", e))
+    let manifest: Manifest = serde_json::from_str(&manifest_content).map_err(|source| {
+        ValidationError::PackConfigInvalid {
+            path: manifest_path,
+            source,
+        }
     })?;
 
     Ok(manifest.header)
 }
 
-/// Creates world pack configuration files for the server.
+/// Adds the test packs to a world's pack configuration files.
+///
+/// Packs the world already uses are kept, so world-attached packs are still loaded. An entry for
+/// one of the test packs is replaced.
 ///
 /// # Arguments
 ///
-/// * `server_path` - Path to the server directory
+/// * `world_path` - Path to the world directory
 /// * `bp_header` - Behavior pack header information
 /// * `rp_header` - Resource pack header information
 ///
 /// # Returns
 ///
-/// * `Ok(())` - If the configuration files were created successfully
-/// * `Err(ValidationError)` - If there was an error creating the files
+/// * `Ok(())` - If the configuration files were written successfully
+/// * `Err(ValidationError)` - If there was an error reading or writing the files
 fn create_world_pack_configs(
-    server_path: &Path,
+    world_path: &Path,
     bp_header: Header,
     rp_header: Header,
 ) -> Result<(), ValidationError> {
-    let world_path = server_path.join("worlds/Bedrock level");
-    fs::create_dir_all(&world_path).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to create world directory: {}", e))
-    })?;
+    fs::create_dir_all(world_path).map_err(|source| pack_copy_failed(world_path, source))?;
 
-    // Create behavior pack config
-    let bp_config = vec![WorldPack {
-        pack_id: bp_header.uuid,
-        version: bp_header.version,
-    }];
-    let bp_config_path = world_path.join("world_behavior_packs.json");
-    fs::write(
-        &bp_config_path,
-        serde_json::to_string_pretty(&bp_config).map_err(|e| {
-            ValidationError::PackCopyFailed(format!("Failed to serialize BP config: {}", e))
-        })?,
-    )
-    .map_err(|e| ValidationError::PackCopyFailed(format!("Failed to write BP config: {}", e)))?;
-
-    // Create resource pack config
-    let rp_config = vec![WorldPack {
-        pack_id: rp_header.uuid,
-        version: rp_header.version,
-    }];
-    let rp_config_path = world_path.join("world_resource_packs.json");
-    fs::write(
-        &rp_config_path,
-        serde_json::to_string_pretty(&rp_config).map_err(|e| {
-            ValidationError::PackCopyFailed(format!("Failed to serialize RP config: {}", e))
-        })?,
-    )
-    .map_err(|e| ValidationError::PackCopyFailed(format!("Failed to write RP config: {}", e)))?;
+    merge_world_pack_config(&world_path.join("world_behavior_packs.json"), bp_header)?;
+    merge_world_pack_config(&world_path.join("world_resource_packs.json"), rp_header)?;
 
     Ok(())
 }
 
+fn merge_world_pack_config(config_path: &Path, header: Header) -> Result<(), ValidationError> {
+    let mut packs: Vec<serde_json::Value> = match fs::read_to_string(config_path) {
+        Ok(content) => {
+            serde_json::from_str(&content).map_err(|source| ValidationError::PackConfigInvalid {
+                path: config_path.to_path_buf(),
+                source,
+            })?
+        }
+        Err(_) => Vec::new(),
+    };
+
+    packs.retain(|pack| pack["pack_id"].as_str() != Some(header.uuid.as_str()));
+    let pack = serde_json::to_value(WorldPack {
+        pack_id: header.uuid,
+        version: header.version,
+    })
+    .map_err(|e| pack_copy_failed(config_path, e.into()))?;
+    packs.push(pack);
+
+    let content = serde_json::to_string_pretty(&packs)
+        .map_err(|e| pack_copy_failed(config_path, e.into()))?;
+    fs::write(config_path, content).map_err(|source| pack_copy_failed(config_path, source))
+}
+
+fn pack_copy_failed(path: &Path, source: std::io::Error) -> ValidationError {
+    ValidationError::PackCopyFailed {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Returns the world the server loads according to its server.properties, relative to the
+/// server directory.
+fn level_world_path(properties: &ServerProperties) -> PathBuf {
+    Path::new("worlds").join(properties.level_name())
+}
+
 /// Creates symlinks to behavior and resource packs in the server directory, removing any existing test packs first.
 ///
+/// The packs are enabled in the world the server's `level-name` property points at.
+///
 /// # Arguments
 ///
 /// * `server_path` - Path to the server directory
@@ -149,6 +275,16 @@ pub fn symlink_test_packs(
     server_path: &Path,
     bp_path: &Path,
     rp_path: &Path,
+) -> Result<(), ValidationError> {
+    let world_path = level_world_path(&read_server_properties(server_path)?);
+    link_test_packs(server_path, bp_path, rp_path, &server_path.join(world_path))
+}
+
+fn link_test_packs(
+    server_path: &Path,
+    bp_path: &Path,
+    rp_path: &Path,
+    world_path: &Path,
 ) -> Result<(), ValidationError> {
     // Validate paths
     if !server_path.exists() || !server_path.is_dir() {
@@ -176,17 +312,10 @@ pub fn symlink_test_packs(
     let rp_dir = server_path.join("resource_packs").join(TESTING_RP_NAME);
 
     let cleanup_path = |path: &Path| -> Result<(), ValidationError> {
-        match fs::metadata(path) {
-            Ok(_) => {
-                if let Err(_) = fs::remove_file(path) {
-                    fs::remove_dir_all(path).map_err(|e| {
-                        ValidationError::PackCopyFailed(format!("Failed to remove existing path: {}", e))
-                    })?;
-                }
-            }
-            Err(_) => {}
+        if fs::metadata(path).is_ok() && fs::remove_file(path).is_err() {
+            fs::remove_dir_all(path).map_err(|source| pack_copy_failed(path, source))?;
         }
-        
+
         Ok(())
     };
 
@@ -194,35 +323,75 @@ pub fn symlink_test_packs(
     cleanup_path(&rp_dir)?;
 
     // Create parent directories if they don't exist
-    fs::create_dir_all(bp_dir.parent().unwrap()).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to create BP directory: {}", e))
-    })?;
-    fs::create_dir_all(rp_dir.parent().unwrap()).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to create RP directory: {}", e))
-    })?;
+    let bp_parent = bp_dir.parent().unwrap();
+    fs::create_dir_all(bp_parent).map_err(|source| pack_copy_failed(bp_parent, source))?;
+    let rp_parent = rp_dir.parent().unwrap();
+    fs::create_dir_all(rp_parent).map_err(|source| pack_copy_failed(rp_parent, source))?;
 
     // Create symlinks using absolute paths to avoid any relative path issues
-    let bp_abs = fs::canonicalize(bp_path).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to get absolute BP path: {}", e))
-    })?;
-    let rp_abs = fs::canonicalize(rp_path).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to get absolute RP path: {}", e))
-    })?;
+    let bp_abs = fs::canonicalize(bp_path).map_err(|source| pack_copy_failed(bp_path, source))?;
+    let rp_abs = fs::canonicalize(rp_path).map_err(|source| pack_copy_failed(rp_path, source))?;
 
-    std::os::unix::fs::symlink(&bp_abs, &bp_dir).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to create BP symlink: {}", e))
-    })?;
+    std::os::unix::fs::symlink(&bp_abs, &bp_dir)
+        .map_err(|source| pack_copy_failed(&bp_dir, source))?;
 
-    std::os::unix::fs::symlink(&rp_abs, &rp_dir).map_err(|e| {
-        ValidationError::PackCopyFailed(format!("Failed to create RP symlink: {}", e))
-    })?;
+    std::os::unix::fs::symlink(&rp_abs, &rp_dir)
+        .map_err(|source| pack_copy_failed(&rp_dir, source))?;
 
     // Create world pack configurations
-    create_world_pack_configs(server_path, bp_header, rp_header)?;
+    create_world_pack_configs(world_path, bp_header, rp_header)?;
+
+    Ok(())
+}
+
+/// Removes the test packs linked by [`symlink_test_packs`] and their world pack configurations.
+///
+/// # Arguments
+///
+/// * `server_path` - Path to the server directory
+///
+/// # Returns
+///
+/// * `Ok(())` - If the test packs were removed or weren't linked
+/// * `Err(ValidationError)` - If there was an error removing them
+pub fn unlink_test_packs(server_path: &Path) -> Result<(), ValidationError> {
+    let world_path = server_path.join(level_world_path(&read_server_properties(server_path)?));
+    let paths = [
+        server_path.join("behavior_packs").join(TESTING_BP_NAME),
+        server_path.join("resource_packs").join(TESTING_RP_NAME),
+        world_path.join("world_behavior_packs.json"),
+        world_path.join("world_resource_packs.json"),
+    ];
+
+    for path in paths {
+        // Don't follow the link, the pack it points to must be left alone
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        let removed = if metadata.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        removed.map_err(|source| pack_copy_failed(&path, source))?;
+    }
 
     Ok(())
 }
 
+/// Options for [`start_server_with`].
+#[derive(Debug, Clone, Default)]
+pub struct StartOptions {
+    /// How long to wait for the server.
+    pub timeouts: Timeouts,
+    /// Whether the server also writes its content log file, whose entries are merged with the
+    /// console output. The content written during the run is kept in
+    /// [`ValidationResult::content_log`].
+    pub content_log_file: bool,
+    /// Whether every raw server log line is reported as an [`Event::ServerLog`].
+    pub verbose: bool,
+}
+
 /// Starts the Bedrock server from the specified directory and monitors its output.
 ///
 /// # Arguments
@@ -233,103 +402,216 @@ pub fn symlink_test_packs(
 ///
 /// * `Ok(ValidationResult)` - The validation results from the server output
 /// * `Err(ValidationError)` - If there was an error starting or monitoring the server
-pub async fn start_server(server_path: &Path, last_log_timeout: Option<u64>, verbose: bool) -> Result<ValidationResult, ValidationError> {
-    if !server_path.exists() || !server_path.is_dir() {
-        return Err(ValidationError::InvalidServerPath(
-            "Server path does not exist or is not a directory".to_string(),
-        ));
-    }
+pub async fn start_server(
+    server_path: &Path,
+    last_log_timeout: Option<u64>,
+    verbose: bool,
+) -> Result<ValidationResult, ValidationError> {
+    let options = StartOptions {
+        timeouts: Timeouts {
+            last_log: Duration::from_secs(last_log_timeout.unwrap_or(2)),
+            ..Default::default()
+        },
+        verbose,
+        ..Default::default()
+    };
+    start_server_with(server_path, &options).await
+}
 
-    let server_exe = server_path.join("bedrock_server");
-    if !server_exe.exists() {
-        return Err(ValidationError::InvalidServerPath(
-            "bedrock_server executable not found".to_string(),
-        ));
+/// Starts the Bedrock server from the specified directory with `options` and monitors its
+/// output.
+///
+/// With the content log file enabled, server.properties is changed to enable it for the run and
+/// restored afterwards.
+///
+/// # Arguments
+///
+/// * `server_path` - Path to the server directory containing bedrock_server
+/// * `options` - Timeouts, content log and verbosity for the run
+///
+/// # Returns
+///
+/// * `Ok(ValidationResult)` - The validation results from the server output
+/// * `Err(ValidationError)` - If there was an error starting or monitoring the server
+pub async fn start_server_with(
+    server_path: &Path,
+    options: &StartOptions,
+) -> Result<ValidationResult, ValidationError> {
+    let snapshot = if options.content_log_file {
+        let lock = SnapshotLock::acquire(server_path)?;
+        let mut properties = read_server_properties(server_path)?;
+        properties.set(CONTENT_LOG_FILE_ENABLED, true);
+        let snapshot = lock.capture(&[Path::new(SERVER_PROPERTIES_FILE_NAME)])?;
+        properties.write(&server_path.join(SERVER_PROPERTIES_FILE_NAME))?;
+        Some(snapshot)
+    } else {
+        None
+    };
+
+    let result = monitor_server(
+        server_path,
+        options.timeouts,
+        options.content_log_file,
+        options.verbose,
+        &CancellationToken::new(),
+    )
+    .await?;
+    if let Some(snapshot) = snapshot {
+        snapshot.restore()?;
     }
 
-    println!("{}", "Making server executable...".cyan());
-    Command::new("chmod")
-        .arg("+x")
-        .arg(&server_exe)
-        .output()
-        .map_err(|e| {
-            ValidationError::ServerStartFailed(format!("Failed to chmod server: {}", e))
-        })?;
+    Ok(result)
+}
 
-    println!("{}", "Starting server process...".cyan());
-    let mut child = TokioCommand::new(&server_exe)
-        .current_dir(server_path)
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| {
-            ValidationError::ServerStartFailed(format!("Failed to start server: {}", e))
-        })?;
+async fn monitor_server(
+    server_path: &Path,
+    timeouts: Timeouts,
+    content_log_file: bool,
+    verbose: bool,
+    cancel: &CancellationToken,
+) -> Result<ValidationResult, ValidationError> {
+    // Followed while the server runs, and merged once its output is complete
+    let mut content_log = content_log_file.then(|| ContentLogTail::start(server_path));
+    let mut content_log_text = String::new();
+    let mut content_log_poll = interval(CONTENT_LOG_POLL_INTERVAL);
 
-    let stdout = child.stdout.take().ok_or_else(|| {
-        ValidationError::ServerStartFailed("Failed to capture server stdout".to_string())
-    })?;
-    let stderr = child.stderr.take().ok_or_else(|| {
-        ValidationError::ServerStartFailed("Failed to capture server stderr".to_string())
-    })?;
+    report(Event::ServerPhase(ServerPhase::Starting));
+    let mut server = ServerInstance::start(server_path).await?;
 
     let mut validation_result = ValidationResult {
         errors: Vec::new(),
         warnings: Vec::new(),
         info: Vec::new(),
+        server_version: None,
+        content_log: None,
     };
 
-    println!("{}", "Monitoring server output...".cyan());
-    let mut stdout_reader = BufReader::new(stdout).lines();
-    let mut stderr_reader = BufReader::new(stderr).lines();
     let mut last_log_time = Instant::now();
+    let mut last_line_at = TokioInstant::now();
     let mut telemetry_seen = false;
     let mut server_started = false;
     let mut telemetry_complete = false;
 
+    let startup_deadline = timeouts
+        .startup
+        .map(|startup| TokioInstant::now() + startup);
+
     loop {
-        let timeout_future = if telemetry_complete {
-            Box::pin(sleep(Duration::from_secs(last_log_timeout.unwrap_or(2))))
+        let timeout_future: Pin<Box<dyn Future<Output = ()> + Send>> = if telemetry_complete {
+            Box::pin(sleep_until(last_line_at + timeouts.last_log))
+        } else if let Some(deadline) = startup_deadline {
+            Box::pin(sleep_until(deadline))
         } else {
-            Box::pin(sleep(Duration::from_secs(0)))
+            Box::pin(std::future::pending())
         };
 
         select! {
-            Ok(Some(line)) = stdout_reader.next_line() => {
-                let line = line.trim();
-                if !line.is_empty() {
-                    process_line(line, &mut validation_result, &mut last_log_time, &mut telemetry_seen, &mut server_started, &mut telemetry_complete, verbose)?;
-                }
+            _ = cancel.cancelled() => {
+                report(Event::ServerPhase(ServerPhase::Stopping));
+                server.stop().await?;
+                report(Event::ServerPhase(ServerPhase::Stopped));
+                return Err(ValidationError::Cancelled);
             }
-            Ok(Some(line)) = stderr_reader.next_line() => {
-                let line = line.trim();
-                if !line.is_empty() {
-                    process_line(line, &mut validation_result, &mut last_log_time, &mut telemetry_seen, &mut server_started, &mut telemetry_complete, verbose)?;
+            line = server.next_line() => match line {
+                Some(line) => {
+                    last_line_at = TokioInstant::now();
+                    process_line(&line, &mut validation_result, &mut last_log_time, &mut telemetry_seen, &mut server_started, &mut telemetry_complete, verbose)?;
+                }
+                None if telemetry_complete => break,
+                None => {
+                    if let Some(tail) = &mut content_log {
+                        // The early exit is the error to report, so a failed read only loses
+                        // the rest of the file
+                        attach_content_log(tail, content_log_text, &mut validation_result).ok();
+                    }
+                    return Err(ValidationError::ServerExited {
+                        result: Box::new(validation_result),
+                    });
+                }
+            },
+            _ = content_log_poll.tick(), if content_log.is_some() => {
+                if let Some(tail) = &mut content_log {
+                    content_log_text.push_str(&tail.read_new()?);
                 }
             }
             _ = timeout_future => {
                 if telemetry_complete {
-                    println!("{}", format!("\nNo new logs for {} seconds, validation complete.", last_log_timeout.unwrap_or(2)).yellow());
+                    report(Event::ServerPhase(ServerPhase::Idle(timeouts.last_log)));
                     break;
                 }
+                if let Some(tail) = &mut content_log {
+                    // Killed first, so whatever the server wrote before hanging is in the file
+                    server.kill().await.ok();
+                    attach_content_log(tail, content_log_text, &mut validation_result).ok();
+                }
+                return Err(ValidationError::StartupTimeout {
+                    seconds: timeouts.startup.unwrap_or_default().as_secs(),
+                    result: Box::new(validation_result),
+                });
             }
-            else => break
         }
     }
 
-    println!("{}", "Stopping server...".cyan());
-    child
-        .kill()
-        .await
-        .map_err(|e| ValidationError::ServerStartFailed(format!("Failed to stop server: {}", e)))?;
-    child.wait().await.map_err(|e| {
-        ValidationError::ServerStartFailed(format!("Failed to wait for server to stop: {}", e))
-    })?;
-    println!("{}", "Server stopped.".green());
+    report(Event::ServerPhase(ServerPhase::Stopping));
+    server.stop().await?;
+    report(Event::ServerPhase(ServerPhase::Stopped));
+
+    // The server has flushed the file once it stopped
+    if let Some(tail) = &mut content_log {
+        attach_content_log(tail, content_log_text, &mut validation_result)?;
+    }
 
     Ok(validation_result)
 }
 
+/// Reads the rest of the content log, merges its entries into `validation_result` and keeps
+/// the whole content there.
+///
+/// What was read before is attached even if the last read fails.
+fn attach_content_log(
+    tail: &mut ContentLogTail,
+    mut content: String,
+    validation_result: &mut ValidationResult,
+) -> Result<(), ContentLogError> {
+    let read = tail.read_new().map(|new| content.push_str(&new));
+    merge_content_log(&content, validation_result);
+    validation_result.content_log = Some(content);
+    read
+}
+
+/// Adds the entries of the content log file that weren't also printed to the console.
+///
+/// Every console entry accounts for one file entry, so an entry logged more often to the file
+/// than to the console is added for each extra time.
+fn merge_content_log(content: &str, validation_result: &mut ValidationResult) {
+    let mut console: Vec<ContentLogEntry> = validation_result
+        .errors
+        .iter()
+        .chain(&validation_result.warnings)
+        .chain(&validation_result.info)
+        .filter_map(|line| ContentLogEntry::parse_console_line(line))
+        .collect();
+
+    for entry in content.lines().filter_map(ContentLogEntry::parse_file_line) {
+        if let Some(index) = console.iter().position(|logged| logged.same_entry(&entry)) {
+            console.swap_remove(index);
+            continue;
+        }
+
+        let line = entry.to_console_line();
+        let entries = match entry.level {
+            LogLevel::Error => &mut validation_result.errors,
+            LogLevel::Warning => &mut validation_result.warnings,
+            LogLevel::Info => &mut validation_result.info,
+        };
+        entries.push(line.clone());
+        report(Event::LogEntry {
+            level: entry.level,
+            line,
+        });
+    }
+}
+
 fn process_line(
     line: &str,
     validation_result: &mut ValidationResult,
@@ -339,22 +621,30 @@ fn process_line(
     telemetry_complete: &mut bool,
     verbose: bool,
 ) -> Result<(), ValidationError> {
-
     if verbose {
-        println!("{}", format!("{}", line).dimmed());
+        report(Event::ServerLog(line.to_string()));
+    }
+
+    // Record the build the server reports, before any packs are loaded
+    if validation_result.server_version.is_none()
+        && !*server_started
+        && let Some(version) = parse_version_line(line)
+    {
+        validation_result.server_version = Some(version);
+        return Ok(());
     }
 
     // Check if server has started
     if line.contains("Server started.") {
         *server_started = true;
-        println!("{}", "Server has started successfully".green());
+        report(Event::ServerPhase(ServerPhase::Started));
         return Ok(());
     }
 
     // Check if we've seen the telemetry message
     if line.contains("TELEMETRY MESSAGE") {
         *telemetry_seen = true;
-        println!("{}", "Starting validation...".cyan());
+        report(Event::ServerPhase(ServerPhase::Validating));
         *last_log_time = Instant::now();
         return Ok(());
     }
@@ -377,18 +667,730 @@ fn process_line(
         *last_log_time = Instant::now();
     }
 
-    // Categorize and print the log message
-    if line.contains("ERROR") {
-        validation_result.errors.push(line.to_string());
+    // Categorize and report the log message
+    let (level, entries) = if line.contains("ERROR") {
+        (LogLevel::Error, &mut validation_result.errors)
     } else if line.contains("WARN") {
-        validation_result.warnings.push(line.to_string());
+        (LogLevel::Warning, &mut validation_result.warnings)
     } else if line.contains("INFO") {
-        validation_result.info.push(line.to_string());
-        // On verbose, we already printed this line
-        if !verbose {
-            println!("{}", format!("{}", line).blue());
+        (LogLevel::Info, &mut validation_result.info)
+    } else {
+        return Ok(());
+    };
+    entries.push(line.to_string());
+    report(Event::LogEntry {
+        level,
+        line: line.to_string(),
+    });
+
+    Ok(())
+}
+
+/// The outcome of a [`Validator`] run.
+#[derive(Debug)]
+pub struct ValidationReport {
+    /// The install directory name of the server validated against, e.g. `1.21.84.1`.
+    pub version: String,
+    /// Path to the server directory validated against.
+    pub server_path: PathBuf,
+    /// The log entries collected while the packs were loaded.
+    pub result: ValidationResult,
+    /// How long the whole validation took.
+    pub duration: Duration,
+}
+
+impl ValidationReport {
+    /// Returns whether the server logged any errors while loading the packs.
+    pub fn has_errors(&self) -> bool {
+        !self.result.errors.is_empty()
+    }
+
+    /// Returns whether the server logged any warnings while loading the packs.
+    pub fn has_warnings(&self) -> bool {
+        !self.result.warnings.is_empty()
+    }
+}
+
+/// Validates a behavior and resource pack against an installed server.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), bedrockci::validate::ValidationError> {
+/// use bedrockci::validate::Validator;
+///
+/// let report = Validator::builder()
+///     .server_version("1.21.84.1")
+///     .behavior_pack("packs/BP")
+///     .resource_pack("packs/RP")
+///     .run()
+///     .await?;
+/// assert!(!report.has_errors());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Validator {
+    server_path: PathBuf,
+    server_version: Option<String>,
+    behavior_pack: PathBuf,
+    resource_pack: PathBuf,
+    world: Option<PathBuf>,
+    properties: ServerProperties,
+    content_log_file: bool,
+    timeouts: Timeouts,
+    verbose: bool,
+    cancel: CancellationToken,
+}
+
+/// Builds a [`Validator`]. Both packs are required.
+#[derive(Debug, Clone, Default)]
+pub struct ValidatorBuilder {
+    server_path: Option<PathBuf>,
+    server_version: Option<String>,
+    behavior_pack: Option<PathBuf>,
+    resource_pack: Option<PathBuf>,
+    world: Option<PathBuf>,
+    properties: ServerProperties,
+    content_log_file: bool,
+    timeouts: Timeouts,
+    verbose: bool,
+    cancel: CancellationToken,
+}
+
+impl ValidatorBuilder {
+    /// Sets the directory holding the installed server versions. Defaults to the configured
+    /// server path.
+    pub fn server_path(mut self, server_path: impl Into<PathBuf>) -> Self {
+        self.server_path = Some(server_path.into());
+        self
+    }
+
+    /// Sets the install directory name of the server to validate against, or `preview` for the
+    /// newest installed preview. Defaults to the newest installed release.
+    pub fn server_version(mut self, version: impl Into<String>) -> Self {
+        self.server_version = Some(version.into());
+        self
+    }
+
+    /// Sets the behavior pack directory to validate.
+    pub fn behavior_pack(mut self, path: impl Into<PathBuf>) -> Self {
+        self.behavior_pack = Some(path.into());
+        self
+    }
+
+    /// Sets the resource pack directory to validate.
+    pub fn resource_pack(mut self, path: impl Into<PathBuf>) -> Self {
+        self.resource_pack = Some(path.into());
+        self
+    }
+
+    /// Sets a world directory or `.mcworld` file to validate in, instead of a fresh world.
+    ///
+    /// The world is copied into the server for each run, so the template is never modified.
+    pub fn world(mut self, path: impl Into<PathBuf>) -> Self {
+        self.world = Some(path.into());
+        self
+    }
+
+    /// Overrides a server.properties value for each run, e.g. `server-port` or `level-seed`.
+    ///
+    /// The server's own server.properties is restored after the run.
+    pub fn property(mut self, key: &str, value: impl ToString) -> Self {
+        self.properties.set(key, value);
+        self
+    }
+
+    /// Overrides every value of `properties`, e.g. read from a properties file, in addition to
+    /// any set with [`ValidatorBuilder::property`].
+    pub fn properties(mut self, properties: &ServerProperties) -> Self {
+        self.properties.merge(properties);
+        self
+    }
+
+    /// Sets whether the server also writes its content log file, whose entries are merged with
+    /// the console output. Some entries are only written to the file.
+    ///
+    /// The content written during the run is kept in [`ValidationResult::content_log`].
+    pub fn content_log_file(mut self, enabled: bool) -> Self {
+        self.content_log_file = enabled;
+        self
+    }
+
+    /// Sets how long validation waits for the server.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Sets whether every raw server log line is reported as an [`Event::ServerLog`].
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Sets a token that aborts validation when cancelled, returning
+    /// [`ValidationError::Cancelled`]. The server is stopped and the test packs are unlinked.
+    pub fn cancel_token(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Builds the validator.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Validator)` - The configured validator
+    /// * `Err(ValidationError)` - If a pack is missing or the server path can't be determined
+    pub fn build(self) -> Result<Validator, ValidationError> {
+        let behavior_pack = self.behavior_pack.ok_or_else(|| {
+            ValidationError::InvalidPackPath("No behavior pack given".to_string())
+        })?;
+        let resource_pack = self.resource_pack.ok_or_else(|| {
+            ValidationError::InvalidPackPath("No resource pack given".to_string())
+        })?;
+        let server_path = match self.server_path {
+            Some(server_path) => server_path,
+            None => get_server_path(false)?,
+        };
+
+        Ok(Validator {
+            server_path,
+            server_version: self.server_version,
+            behavior_pack,
+            resource_pack,
+            world: self.world,
+            properties: self.properties,
+            content_log_file: self.content_log_file,
+            timeouts: self.timeouts,
+            verbose: self.verbose,
+            cancel: self.cancel,
+        })
+    }
+
+    /// Builds the validator and runs it.
+    pub async fn run(self) -> Result<ValidationReport, ValidationError> {
+        self.build()?.run().await
+    }
+}
+
+impl Validator {
+    pub fn builder() -> ValidatorBuilder {
+        ValidatorBuilder::default()
+    }
+
+    /// Links the packs into the server, runs it until the packs are loaded and collects the log
+    /// entries they produced.
+    ///
+    /// Each run starts from a fresh world. The previous world, anything at the test pack paths
+    /// and server.properties are snapshotted first and restored afterwards, whether or not
+    /// validation succeeds.
+    ///
+    /// The property overrides are applied on top of the server's own server.properties, and
+    /// content log console output is always enabled, since pack errors are only read from the
+    /// console.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ValidationReport)` - The collected log entries, whether or not they include errors
+    /// * `Err(ValidationError)` - If the server isn't installed, the packs are invalid or the
+    ///   server failed to run
+    pub async fn run(&self) -> Result<ValidationReport, ValidationError> {
+        let started_at = Instant::now();
+        let version =
+            resolve_installed_version_in(&self.server_path, self.server_version.as_deref())?
+                .ok_or_else(|| {
+                    ValidationError::ServerNotInstalled(
+                        self.server_version
+                            .clone()
+                            .unwrap_or_else(|| "latest".to_string()),
+                    )
+                })?;
+        let server_path = self.server_path.join(&version);
+        if version.starts_with('.') || !server_path.is_dir() {
+            return Err(ValidationError::ServerNotInstalled(version));
+        }
+
+        if self.cancel.is_cancelled() {
+            return Err(ValidationError::Cancelled);
         }
+
+        // Read the server's own properties, not those of a run that was killed
+        let lock = SnapshotLock::acquire(&server_path)?;
+        let mut properties = read_server_properties(&server_path)?;
+        properties.merge(&self.properties);
+        properties.set(CONTENT_LOG_CONSOLE_OUTPUT_ENABLED, true);
+        if self.content_log_file {
+            properties.set(CONTENT_LOG_FILE_ENABLED, true);
+        }
+        if self.world.is_some() {
+            properties.set(LEVEL_NAME, TEMPLATE_LEVEL_NAME);
+        }
+        let world_path = level_world_path(&properties);
+
+        // Run against a fresh world, and put back whatever was there before even if
+        // validation fails
+        let bp_link = Path::new("behavior_packs").join(TESTING_BP_NAME);
+        let rp_link = Path::new("resource_packs").join(TESTING_RP_NAME);
+        let mut paths = vec![
+            world_path.as_path(),
+            &bp_link,
+            &rp_link,
+            Path::new(SERVER_PROPERTIES_FILE_NAME),
+        ];
+        // The content log files of the run must not be left behind in the install either
+        if self.content_log_file {
+            paths.push(Path::new(CONTENT_LOG_DIR));
+        }
+        let snapshot = lock.capture(&paths)?;
+        properties.write(&server_path.join(SERVER_PROPERTIES_FILE_NAME))?;
+
+        if let Some(template) = &self.world {
+            install_world(template, &server_path.join(&world_path))?;
+        }
+
+        report(Event::ServerPhase(ServerPhase::LinkingPacks));
+        link_test_packs(
+            &server_path,
+            &self.behavior_pack,
+            &self.resource_pack,
+            &server_path.join(&world_path),
+        )?;
+
+        let result = monitor_server(
+            &server_path,
+            self.timeouts,
+            self.content_log_file,
+            self.verbose,
+            &self.cancel,
+        )
+        .await?;
+        snapshot.restore()?;
+
+        Ok(ValidationReport {
+            version,
+            server_path,
+            result,
+            duration: started_at.elapsed(),
+        })
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::SNAPSHOT_DIR_NAME;
+    use crate::test_support::FakeServer;
+
+    const WORLD_PATH: &str = "worlds/Bedrock level";
+
+    /// Appends the output of a server loading the packs with one error and one warning.
+    fn loads_packs(server: FakeServer) -> FakeServer {
+        server
+            .started()
+            .log("INFO", "TELEMETRY MESSAGE")
+            .log("INFO", "Server Telemetry is currently not enabled.")
+            .run("echo '======================================================'")
+            .log("ERROR", "[Scripting] Plugin [TEST] - module not found")
+            .log_stderr("WARN", "[Json] Unknown field")
+            .console()
+    }
+
+    /// Creates a server path with a fake server installed as `version`, plus both packs.
+    fn fixture(version: &str, server: FakeServer) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        server.write(&dir.path().join("servers").join(version));
+
+        for (pack, uuid) in [
+            ("BP", "8e4d2f3a-0000-4000-8000-000000000001"),
+            ("RP", "8e4d2f3a-0000-4000-8000-000000000002"),
+        ] {
+            fs::create_dir_all(dir.path().join(pack)).unwrap();
+            fs::write(
+                dir.path().join(pack).join("manifest.json"),
+                format!(
+                    r#"{{"header": {{"uuid": "{}", "version": [1, 0, 0]}}}}"#,
+                    uuid
+                ),
+            )
+            .unwrap();
+        }
+        dir
+    }
+
+    fn validator(dir: &Path) -> ValidatorBuilder {
+        Validator::builder()
+            .server_path(dir.join("servers"))
+            .behavior_pack(dir.join("BP"))
+            .resource_pack(dir.join("RP"))
+            .timeouts(Timeouts {
+                last_log: Duration::from_millis(200),
+                startup: Some(Duration::from_secs(10)),
+            })
+    }
+
+    #[tokio::test]
+    async fn test_validator_collects_log_entries() {
+        let dir = fixture("1.21.84.1", loads_packs(FakeServer::new()));
+
+        let report = validator(dir.path()).run().await.unwrap();
+
+        assert_eq!(report.version, "1.21.84.1");
+        assert_eq!(report.result.server_version.as_deref(), Some("1.21.84.1"));
+        assert!(report.has_errors());
+        assert!(report.has_warnings());
+        assert_eq!(report.result.errors.len(), 1);
+        assert!(report.result.errors[0].contains("module not found"));
+        assert!(report.result.info.is_empty());
+        assert_unlinked(&report.server_path);
+    }
+
+    #[tokio::test]
+    async fn test_validator_resets_and_restores_world() {
+        // Records whether the run saw the previous world, then changes it
+        let prelude = "[ -e 'worlds/Bedrock level/level.dat' ] && touch saw-previous-world
+echo changed > 'worlds/Bedrock level/level.dat'";
+
+        for server in [
+            loads_packs(FakeServer::new().run(prelude)),
+            FakeServer::new().run(prelude).run("exit 1"),
+        ] {
+            let dir = fixture("1.21.84.1", server);
+            let server_path = dir.path().join("servers/1.21.84.1");
+            fs::create_dir_all(server_path.join(WORLD_PATH)).unwrap();
+            fs::write(server_path.join(WORLD_PATH).join("level.dat"), "original").unwrap();
+
+            validator(dir.path()).run().await.ok();
+
+            assert!(!server_path.join("saw-previous-world").exists());
+            assert_eq!(
+                fs::read_to_string(server_path.join(WORLD_PATH).join("level.dat")).unwrap(),
+                "original"
+            );
+            assert_unlinked(&server_path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validator_fails_when_server_never_loads_packs() {
+        let dir = fixture("1.21.84.1", FakeServer::new().run("exec sleep 30"));
+        let result = validator(dir.path())
+            .timeouts(Timeouts {
+                last_log: Duration::from_millis(200),
+                startup: Some(Duration::from_millis(200)),
+            })
+            .run()
+            .await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::StartupTimeout { .. })
+        ));
+
+        // Errors logged before the server exits are still reported
+        let dir = fixture(
+            "1.21.84.1",
+            FakeServer::new()
+                .started()
+                .log("ERROR", "[Scripting] Plugin [TEST] - module not found"),
+        );
+        let error = validator(dir.path()).run().await.unwrap_err();
+        assert!(matches!(error, ValidationError::ServerExited { .. }));
+        let partial = error.partial_result().unwrap();
+        assert_eq!(partial.server_version.as_deref(), Some("1.21.84.1"));
+        assert_eq!(partial.errors.len(), 1);
+    }
+
+    fn assert_unlinked(server_path: &Path) {
+        for path in [
+            "behavior_packs/TESTING_PACK_BP",
+            "resource_packs/TESTING_PACK_RP",
+            "worlds/Bedrock level/world_behavior_packs.json",
+        ] {
+            assert!(
+                fs::symlink_metadata(server_path.join(path)).is_err(),
+                "{}",
+                path
+            );
+        }
+        assert!(!server_path.join(SNAPSHOT_DIR_NAME).exists());
+    }
+
+    #[tokio::test]
+    async fn test_validator_uses_template_world() {
+        // Records the level name and the world's behavior packs the server was started with
+        let server = FakeServer::new()
+            .run("grep level-name server.properties > seen-level-name")
+            .run("cat 'worlds/BedrockCI template/world_behavior_packs.json' > seen-packs.json");
+        let dir = fixture("1.21.84.1", loads_packs(server));
+        let server_path = dir.path().join("servers/1.21.84.1");
+        let properties = "server-name=Test\nlevel-name=Bedrock level\n";
+        fs::write(server_path.join(SERVER_PROPERTIES_FILE_NAME), properties).unwrap();
+
+        let template = dir.path().join("template");
+        fs::create_dir_all(&template).unwrap();
+        fs::write(template.join("level.dat"), "level").unwrap();
+        let world_packs =
+            r#"[{"pack_id": "0a1b2c3d-0000-4000-8000-000000000003", "version": [2, 0, 0]}]"#;
+        fs::write(template.join("world_behavior_packs.json"), world_packs).unwrap();
+
+        validator(dir.path()).world(&template).run().await.unwrap();
+
+        assert_eq!(
+            fs::read_to_string(server_path.join("seen-level-name")).unwrap(),
+            "level-name=BedrockCI template\n"
+        );
+        let seen: Vec<serde_json::Value> =
+            serde_json::from_str(&fs::read_to_string(server_path.join("seen-packs.json")).unwrap())
+                .unwrap();
+        let pack_ids: Vec<&str> = seen
+            .iter()
+            .map(|pack| pack["pack_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            pack_ids,
+            [
+                "0a1b2c3d-0000-4000-8000-000000000003",
+                "8e4d2f3a-0000-4000-8000-000000000001"
+            ]
+        );
+
+        assert_eq!(
+            fs::read_to_string(template.join("world_behavior_packs.json")).unwrap(),
+            world_packs
+        );
+        assert_eq!(
+            fs::read_to_string(server_path.join(SERVER_PROPERTIES_FILE_NAME)).unwrap(),
+            properties
+        );
+        assert!(!server_path.join("worlds/BedrockCI template").exists());
+    }
+
+    #[tokio::test]
+    async fn test_validator_applies_and_reverts_property_overrides() {
+        // Records the properties the server was started with
+        let server = FakeServer::new().run("cat server.properties > seen.properties");
+        let dir = fixture("1.21.84.1", loads_packs(server));
+        let server_path = dir.path().join("servers/1.21.84.1");
+        let properties = "server-name=Test\ngamemode=survival\nserver-port=19132\n";
+        fs::write(server_path.join(SERVER_PROPERTIES_FILE_NAME), properties).unwrap();
+
+        validator(dir.path())
+            .property("gamemode", "creative")
+            .properties(&ServerProperties::parse(
+                "server-port=19200\nlevel-seed=42\n",
+            ))
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(server_path.join("seen.properties")).unwrap(),
+            "server-name=Test
+gamemode=creative
+server-port=19200
+level-seed=42
+content-log-console-output-enabled=true
+"
+        );
+        assert_eq!(
+            fs::read_to_string(server_path.join(SERVER_PROPERTIES_FILE_NAME)).unwrap(),
+            properties
+        );
+        assert_unlinked(&server_path);
+    }
+
+    #[tokio::test]
+    async fn test_validator_merges_content_log_file() {
+        // Writes a content log file when enabled, repeating one of the console errors
+        let server = FakeServer::new().run(
+            "if grep -q content-log-file-enabled=true server.properties; then
+  mkdir -p logs
+  echo '10:00:00[Scripting][error]-Plugin [TEST] - module not found' >> logs/ContentLog__run.txt
+  echo '10:00:01[Json][warning]-Only in the file' >> logs/ContentLog__run.txt
+fi",
+        );
+        let dir = fixture("1.21.84.1", loads_packs(server));
+        let server_path = dir.path().join("servers/1.21.84.1");
+        fs::create_dir_all(server_path.join("logs")).unwrap();
+        fs::write(
+            server_path.join("logs/ContentLog__run.txt"),
+            "09:00:00[Json][error]-From an earlier run\n",
+        )
+        .unwrap();
+
+        let report = validator(dir.path())
+            .content_log_file(true)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(report.result.errors.len(), 1);
+        assert_eq!(
+            report.result.warnings[1],
+            "[10:00:01 WARN] [Json] Only in the file"
+        );
+        assert_eq!(report.result.warnings.len(), 2);
+        assert_eq!(
+            report.result.content_log.as_deref(),
+            Some(
+                "10:00:00[Scripting][error]-Plugin [TEST] - module not found
+10:00:01[Json][warning]-Only in the file
+"
+            )
+        );
+        assert!(
+            !fs::read_to_string(server_path.join(SERVER_PROPERTIES_FILE_NAME))
+                .unwrap_or_default()
+                .contains(CONTENT_LOG_FILE_ENABLED)
+        );
+        assert_eq!(
+            fs::read_to_string(server_path.join("logs/ContentLog__run.txt")).unwrap(),
+            "09:00:00[Json][error]-From an earlier run\n"
+        );
+
+        let report = validator(dir.path()).run().await.unwrap();
+        assert_eq!(report.result.content_log, None);
+        assert_eq!(report.result.warnings.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_content_log_is_attached_when_server_exits_early() {
+        let server = FakeServer::new()
+            .started()
+            .run("mkdir -p logs && echo '10:00:00[Json][error]-Only in the file' > logs/ContentLog__run.txt")
+            .run("exit 1");
+        let dir = fixture("1.21.84.1", server);
+
+        let error = validator(dir.path())
+            .content_log_file(true)
+            .run()
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ValidationError::ServerExited { .. }));
+        let partial = error.partial_result().unwrap();
+        assert_eq!(
+            partial.content_log.as_deref(),
+            Some("10:00:00[Json][error]-Only in the file\n")
+        );
+        assert_eq!(partial.errors, ["[10:00:00 ERROR] [Json] Only in the file"]);
+    }
+
+    #[tokio::test]
+    async fn test_start_server_with_content_log_file() {
+        let server = FakeServer::new().run(
+            "grep -q content-log-file-enabled=true server.properties || exit 1
+mkdir -p logs
+echo '10:00:01[Json][warning]-Only in the file' > logs/ContentLog__run.txt",
+        );
+        let dir = tempfile::tempdir().unwrap();
+        loads_packs(server).write(dir.path());
+        fs::write(
+            dir.path().join(SERVER_PROPERTIES_FILE_NAME),
+            "server-port=19132\n",
+        )
+        .unwrap();
+
+        let options = StartOptions {
+            timeouts: Timeouts {
+                last_log: Duration::from_millis(200),
+                startup: Some(Duration::from_secs(10)),
+            },
+            content_log_file: true,
+            ..Default::default()
+        };
+        let result = start_server_with(dir.path(), &options).await.unwrap();
+
+        assert_eq!(result.warnings.len(), 2);
+        assert_eq!(
+            result.content_log.as_deref(),
+            Some("10:00:01[Json][warning]-Only in the file\n")
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join(SERVER_PROPERTIES_FILE_NAME)).unwrap(),
+            "server-port=19132\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_content_log_counts_console_entries() {
+        let mut result = ValidationResult {
+            errors: vec!["[10:00:00 ERROR] [Json] Broken".to_string()],
+            warnings: Vec::new(),
+            info: Vec::new(),
+            server_version: None,
+            content_log: None,
+        };
+
+        merge_content_log(
+            "10:00:00[Json][error]-Broken\n10:00:01[Json][error]-Broken\n",
+            &mut result,
+        );
+
+        assert_eq!(
+            result.errors,
+            [
+                "[10:00:00 ERROR] [Json] Broken",
+                "[10:00:01 ERROR] [Json] Broken"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_validation_stops_server_and_unlinks_packs() {
+        let dir = fixture(
+            "1.21.84.1",
+            FakeServer::new()
+                .run("echo $$ > pid")
+                .run("echo 'Server started.'")
+                .console(),
+        );
+        let server_path = dir.path().join("servers/1.21.84.1");
+        let cancel = CancellationToken::new();
+        tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                cancel.cancel();
+            }
+        });
+
+        let result = validator(dir.path()).cancel_token(cancel).run().await;
+
+        assert!(matches!(result, Err(ValidationError::Cancelled)));
+        assert_unlinked(&server_path);
+        let pid = fs::read_to_string(server_path.join("pid")).unwrap();
+        assert!(!Path::new("/proc").join(pid.trim()).exists());
+        assert!(server_path.join("behavior_packs").is_dir());
+        assert!(dir.path().join("BP/manifest.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_validation_unlinks_packs() {
+        let dir = fixture("1.21.84.1", FakeServer::new().run("exec sleep 30"));
+
+        let result =
+            tokio::time::timeout(Duration::from_millis(300), validator(dir.path()).run()).await;
+
+        assert!(result.is_err());
+        assert_unlinked(&dir.path().join("servers/1.21.84.1"));
+    }
+
+    #[tokio::test]
+    async fn test_validator_requires_packs_and_server() {
+        let dir = fixture("1.21.84.1", loads_packs(FakeServer::new()));
+
+        let result = Validator::builder()
+            .server_path(dir.path().join("servers"))
+            .resource_pack(dir.path().join("RP"))
+            .build();
+        assert!(matches!(result, Err(ValidationError::InvalidPackPath(_))));
+
+        let result = validator(dir.path())
+            .server_version("1.21.90.1")
+            .run()
+            .await;
+        assert!(matches!(
+            result,
+            Err(ValidationError::ServerNotInstalled(v)) if v == "1.21.90.1"
+        ));
+    }
 }
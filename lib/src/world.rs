@@ -0,0 +1,166 @@
+use crate::error::ErrorKind;
+use crate::fs_util::copy_dir;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// File every Bedrock world has at its root.
+const LEVEL_DAT: &str = "level.dat";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorldError {
+    #[error("Invalid world {}: {reason}", path.display())]
+    InvalidTemplate { path: PathBuf, reason: String },
+    #[error("Failed to copy world {}", path.display())]
+    CopyFailed {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to extract world {}", path.display())]
+    ExtractFailed {
+        path: PathBuf,
+        #[source]
+        source: zip::result::ZipError,
+    },
+}
+
+impl WorldError {
+    /// Returns the broad category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            // Reading or writing the files failed, rather than the .mcworld being broken
+            WorldError::ExtractFailed {
+                source: zip::result::ZipError::Io(_),
+                ..
+            } => ErrorKind::Infrastructure,
+            WorldError::InvalidTemplate { .. } | WorldError::ExtractFailed { .. } => {
+                ErrorKind::Usage
+            }
+            WorldError::CopyFailed { .. } => ErrorKind::Infrastructure,
+        }
+    }
+}
+
+/// Copies a template world into a server's worlds directory, leaving the template untouched.
+///
+/// # Arguments
+///
+/// * `template` - A world directory, or a `.mcworld` file as exported by the game
+/// * `dest` - The world directory to create, e.g. `worlds/<level-name>` in the server directory
+///
+/// # Returns
+///
+/// * `Ok(())` - If the world was copied and contains a `level.dat`
+/// * `Err(WorldError)` - If the template isn't a world or couldn't be copied
+pub fn install_world(template: &Path, dest: &Path) -> Result<(), WorldError> {
+    let invalid = |reason: &str| WorldError::InvalidTemplate {
+        path: template.to_path_buf(),
+        reason: reason.to_string(),
+    };
+
+    if template.is_dir() {
+        if !template.join(LEVEL_DAT).is_file() {
+            return Err(invalid("no level.dat found"));
+        }
+        copy_dir(template, dest, &|path, source| WorldError::CopyFailed {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    } else if template.is_file() {
+        let extract_failed = |source| WorldError::ExtractFailed {
+            path: template.to_path_buf(),
+            source,
+        };
+        let file = File::open(template).map_err(|source| WorldError::CopyFailed {
+            path: template.to_path_buf(),
+            source,
+        })?;
+        ZipArchive::new(file)
+            .and_then(|mut archive| archive.extract(dest))
+            .map_err(extract_failed)?;
+        if !dest.join(LEVEL_DAT).is_file() {
+            return Err(invalid("no level.dat found at the root of the .mcworld"));
+        }
+    } else {
+        return Err(invalid("not a world directory or .mcworld file"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_install_world_copies_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("template");
+        fs::create_dir_all(template.join("db")).unwrap();
+        fs::write(template.join(LEVEL_DAT), "level").unwrap();
+        fs::write(template.join("db/CURRENT"), "MANIFEST-000001").unwrap();
+
+        let dest = dir.path().join("worlds/test");
+        install_world(&template, &dest).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dest.join("db/CURRENT")).unwrap(),
+            "MANIFEST-000001"
+        );
+        assert!(template.join(LEVEL_DAT).exists());
+    }
+
+    #[test]
+    fn test_install_world_extracts_mcworld() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("test.mcworld");
+        let mut zip = zip::ZipWriter::new(File::create(&template).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file(LEVEL_DAT, options).unwrap();
+        zip.write_all(b"level").unwrap();
+        zip.start_file("levelname.txt", options).unwrap();
+        zip.write_all(b"Test World").unwrap();
+        zip.finish().unwrap();
+
+        let dest = dir.path().join("worlds/test");
+        install_world(&template, &dest).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dest.join("levelname.txt")).unwrap(),
+            "Test World"
+        );
+    }
+
+    #[test]
+    fn test_install_world_rejects_non_worlds() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("worlds/test");
+
+        assert!(matches!(
+            install_world(dir.path(), &dest),
+            Err(WorldError::InvalidTemplate { .. })
+        ));
+        assert!(matches!(
+            install_world(&dir.path().join("missing.mcworld"), &dest),
+            Err(WorldError::InvalidTemplate { .. })
+        ));
+    }
+
+    #[test]
+    fn test_extract_failures_are_classified_by_cause() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("broken.mcworld");
+        fs::write(&template, b"not a zip").unwrap();
+        let broken = install_world(&template, &dir.path().join("worlds/test")).unwrap_err();
+        assert_eq!(broken.kind(), ErrorKind::Usage);
+
+        let disk_failure = WorldError::ExtractFailed {
+            path: template,
+            source: zip::result::ZipError::Io(std::io::Error::other("disk full")),
+        };
+        assert_eq!(disk_failure.kind(), ErrorKind::Infrastructure);
+    }
+}